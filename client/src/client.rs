@@ -107,31 +107,38 @@ impl Client {
 
         let command = std::iter::once("SUBSCRIBE").chain(channels.iter().map(S::as_ref));
         let raw_command = RespValue::Array(command.map(str_to_bulk_string).collect());
-        sub_client.inner.lock().await.send(raw_command).await?;
+        {
+            let mut cxn = sub_client.inner.lock().await;
+            cxn.send(raw_command).await?;
+            // wait for confirmation messages, so that no published messages are missed
+            for _ in 0..channels.len() {
+                timeout(self.config.timeout, cxn.try_next())
+                    .await??
+                    .ok_or(Error::Disconnected)?;
+            }
+        }
 
         let inner_stream = Arc::try_unwrap(sub_client.inner)
             .expect("should only have one reference")
             .into_inner()
             .into_stream();
-        let messages_stream = inner_stream
-            .skip(channels.len()) // skip confirmation messages
-            .map(|parse_result| match parse_result {
-                Ok(raw_val) => match raw_val {
-                    RespValue::Array(mut values) => {
-                        let message = values
-                            .pop()
-                            .and_then(RespValue::into_bytes)
-                            .ok_or(Error::Invalid("No message".into()))?;
-                        let channel = values
-                            .pop()
-                            .and_then(RespValue::into_bytes)
-                            .ok_or(Error::Invalid("No channel".into()))?;
-                        Ok((channel, message))
-                    }
-                    _ => Err(Error::Invalid(format!("Expected array, got {:?}", raw_val))),
-                },
-                Err(err) => Err(Error::Parse(err)),
-            });
+        let messages_stream = inner_stream.map(|parse_result| match parse_result {
+            Ok(raw_val) => match raw_val {
+                RespValue::Array(mut values) => {
+                    let message = values
+                        .pop()
+                        .and_then(RespValue::into_bytes)
+                        .ok_or(Error::Invalid("No message".into()))?;
+                    let channel = values
+                        .pop()
+                        .and_then(RespValue::into_bytes)
+                        .ok_or(Error::Invalid("No channel".into()))?;
+                    Ok((channel, message))
+                }
+                _ => Err(Error::Invalid(format!("Expected array, got {:?}", raw_val))),
+            },
+            Err(err) => Err(Error::Parse(err)),
+        });
 
         Ok(messages_stream)
     }
@@ -180,6 +187,92 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn set_options() -> ClientResult<()> {
+        let client = Client::connect(LOCALHOST).await?;
+        client.send(vec!["DEL", "lock"]).await?;
+
+        let res = client
+            .send(vec!["SET", "lock", "a", "NX", "PX", "3000"])
+            .await?;
+        assert_eq!(res, constants::OK.try_into()?);
+        let res = client
+            .send(vec!["SET", "lock", "b", "NX", "PX", "3000"])
+            .await?;
+        assert_eq!(res, Value::Nil);
+
+        let res = client
+            .send(vec!["SET", "lock", "c", "XX", "KEEPTTL", "GET"])
+            .await?;
+        assert_eq!(res, Value::String(Bytes::from_static(b"a")));
+        assert!(matches!(
+            client.send(vec!["PTTL", "lock"]).await?,
+            Value::Int(1..=3000)
+        ));
+
+        let res = client.send(vec!["GETDEL", "lock"]).await?;
+        assert_eq!(res, Value::String(Bytes::from_static(b"c")));
+        assert_eq!(client.send(vec!["GET", "lock"]).await?, Value::Nil);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn multi_get_and_set() -> ClientResult<()> {
+        let client = Client::connect(LOCALHOST).await?;
+        client
+            .send(vec!["DEL", "mkey:a", "mkey:b", "mkey:c", "mkey:list"])
+            .await?;
+        client.send(vec!["RPUSH", "mkey:list", "x"]).await?;
+
+        let res = client
+            .send(vec!["MSET", "mkey:a", "1", "mkey:b", "2"])
+            .await?;
+        assert_eq!(res, constants::OK.try_into()?);
+        let res = client
+            .send(vec![
+                "MGET",
+                "mkey:a",
+                "mkey:missing",
+                "mkey:list",
+                "mkey:b",
+            ])
+            .await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![
+                Value::String(Bytes::from_static(b"1")),
+                Value::Nil,
+                Value::Nil,
+                Value::String(Bytes::from_static(b"2"))
+            ])
+        );
+
+        // nothing is set if any of the keys exists
+        let res = client
+            .send(vec!["MSETNX", "mkey:c", "3", "mkey:a", "4"])
+            .await?;
+        assert_eq!(res, Value::Int(0));
+        let res = client.send(vec!["MGET", "mkey:a", "mkey:c"]).await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![Value::String(Bytes::from_static(b"1")), Value::Nil])
+        );
+        let res = client.send(vec!["MSETNX", "mkey:c", "3"]).await?;
+        assert_eq!(res, Value::Int(1));
+
+        // MSET discards the previous TTL
+        client.send(vec!["EXPIRE", "mkey:a", "100"]).await?;
+        client.send(vec!["MSET", "mkey:a", "5"]).await?;
+        assert_eq!(client.send(vec!["TTL", "mkey:a"]).await?, Value::Int(-1));
+        assert_eq!(
+            client.send(vec!["GET", "mkey:a"]).await?,
+            Value::String(Bytes::from_static(b"5"))
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn sorted_set() -> ClientResult<()> {
         let client = Client::connect(LOCALHOST).await?;
@@ -201,6 +294,980 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn sorted_set_ranges() -> ClientResult<()> {
+        let client = Client::connect(LOCALHOST).await?;
+        client.send(vec!["DEL", "leaderboard", "podium"]).await?;
+        client
+            .send(vec![
+                "ZADD",
+                "leaderboard",
+                "10",
+                "ann",
+                "30",
+                "bob",
+                "20",
+                "cat",
+                "40",
+                "dan",
+            ])
+            .await?;
+
+        let res = client
+            .send(vec![
+                "ZRANGE",
+                "leaderboard",
+                "+inf",
+                "(10",
+                "BYSCORE",
+                "REV",
+                "LIMIT",
+                "0",
+                "2",
+                "WITHSCORES",
+            ])
+            .await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![
+                Value::String(Bytes::from_static(b"dan")),
+                Value::String(Bytes::from_static(b"40")),
+                Value::String(Bytes::from_static(b"bob")),
+                Value::String(Bytes::from_static(b"30"))
+            ])
+        );
+        let res = client.send(vec!["ZREVRANK", "leaderboard", "cat"]).await?;
+        assert_eq!(res, Value::Int(2));
+
+        let res = client
+            .send(vec![
+                "ZRANGESTORE",
+                "podium",
+                "leaderboard",
+                "0",
+                "2",
+                "REV",
+            ])
+            .await?;
+        assert_eq!(res, Value::Int(3));
+        let res = client.send(vec!["ZRANGE", "podium", "0", "0"]).await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![Value::String(Bytes::from_static(b"cat"))])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sorted_set_add_flags() -> ClientResult<()> {
+        let client = Client::connect(LOCALHOST).await?;
+        client.send(vec!["DEL", "highscores"]).await?;
+        client
+            .send(vec!["ZADD", "highscores", "100", "ann", "200", "bob"])
+            .await?;
+
+        let res = client
+            .send(vec![
+                "ZADD",
+                "highscores",
+                "GT",
+                "CH",
+                "150",
+                "ann",
+                "50",
+                "bob",
+                "10",
+                "cat",
+            ])
+            .await?;
+        assert_eq!(res, Value::Int(2));
+        let res = client.send(vec!["ZSCORE", "highscores", "bob"]).await?;
+        assert_eq!(res, Value::String(Bytes::from_static(b"200")));
+        let res = client
+            .send(vec!["ZADD", "highscores", "XX", "INCR", "5", "dan"])
+            .await?;
+        assert_eq!(res, Value::Nil);
+        let res = client
+            .send(vec!["ZINCRBY", "highscores", "5", "cat"])
+            .await?;
+        assert_eq!(res, Value::String(Bytes::from_static(b"15")));
+        assert!(
+            client
+                .send(vec!["ZADD", "highscores", "NX", "XX", "1", "ann"])
+                .await
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sorted_set_aggregation() -> ClientResult<()> {
+        let client = Client::connect(LOCALHOST).await?;
+        client
+            .send(vec!["DEL", "visits:mon", "visits:tue", "visits:week"])
+            .await?;
+        client
+            .send(vec!["ZADD", "visits:mon", "1", "home", "4", "docs"])
+            .await?;
+        client
+            .send(vec!["ZADD", "visits:tue", "2", "home", "3", "blog"])
+            .await?;
+
+        let res = client
+            .send(vec![
+                "ZUNIONSTORE",
+                "visits:week",
+                "2",
+                "visits:mon",
+                "visits:tue",
+                "WEIGHTS",
+                "1",
+                "2",
+            ])
+            .await?;
+        assert_eq!(res, Value::Int(3));
+        let res = client
+            .send(vec!["ZRANGE", "visits:week", "0", "-1", "WITHSCORES"])
+            .await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![
+                Value::String(Bytes::from_static(b"docs")),
+                Value::String(Bytes::from_static(b"4")),
+                Value::String(Bytes::from_static(b"home")),
+                Value::String(Bytes::from_static(b"5")),
+                Value::String(Bytes::from_static(b"blog")),
+                Value::String(Bytes::from_static(b"6"))
+            ])
+        );
+        let res = client
+            .send(vec![
+                "ZINTER",
+                "2",
+                "visits:mon",
+                "visits:tue",
+                "AGGREGATE",
+                "MAX",
+                "WITHSCORES",
+            ])
+            .await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![
+                Value::String(Bytes::from_static(b"home")),
+                Value::String(Bytes::from_static(b"2"))
+            ])
+        );
+        let res = client
+            .send(vec!["ZDIFF", "2", "visits:mon", "visits:tue"])
+            .await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![Value::String(Bytes::from_static(b"docs"))])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sorted_set_pops() -> ClientResult<()> {
+        let client = Client::connect(LOCALHOST).await?;
+        client.send(vec!["DEL", "scores", "tasks"]).await?;
+        client
+            .send(vec![
+                "ZADD", "scores", "1", "a", "2", "b", "3", "c", "4", "d",
+            ])
+            .await?;
+
+        let res = client.send(vec!["ZCOUNT", "scores", "(1", "3"]).await?;
+        assert_eq!(res, Value::Int(2));
+        let res = client.send(vec!["ZMSCORE", "scores", "b", "x"]).await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![Value::String(Bytes::from_static(b"2")), Value::Nil])
+        );
+        let res = client.send(vec!["ZPOPMAX", "scores", "2"]).await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![
+                Value::String(Bytes::from_static(b"d")),
+                Value::String(Bytes::from_static(b"4")),
+                Value::String(Bytes::from_static(b"c")),
+                Value::String(Bytes::from_static(b"3"))
+            ])
+        );
+        let res = client
+            .send(vec!["ZMPOP", "2", "tasks", "scores", "MIN", "COUNT", "5"])
+            .await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![
+                Value::String(Bytes::from_static(b"scores")),
+                Value::Array(vec![
+                    Value::Array(vec![
+                        Value::String(Bytes::from_static(b"a")),
+                        Value::String(Bytes::from_static(b"1"))
+                    ]),
+                    Value::Array(vec![
+                        Value::String(Bytes::from_static(b"b")),
+                        Value::String(Bytes::from_static(b"2"))
+                    ])
+                ])
+            ])
+        );
+        let res = client.send(vec!["ZCARD", "scores"]).await?;
+        assert_eq!(res, Value::Int(0));
+
+        client
+            .send(vec!["ZADD", "tasks", "0", "a", "0", "b", "0", "c"])
+            .await?;
+        let res = client.send(vec!["ZLEXCOUNT", "tasks", "[b", "+"]).await?;
+        assert_eq!(res, Value::Int(2));
+        let res = client
+            .send(vec!["ZREMRANGEBYLEX", "tasks", "-", "(c"])
+            .await?;
+        assert_eq!(res, Value::Int(2));
+        let res = client
+            .send(vec!["ZREMRANGEBYRANK", "tasks", "0", "-1"])
+            .await?;
+        assert_eq!(res, Value::Int(1));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn blocking_sorted_set_pop() -> ClientResult<()> {
+        let client = Client::connect(LOCALHOST).await?;
+        client
+            .send(vec!["DEL", "queue:urgent", "queue:normal"])
+            .await?;
+
+        let res = client
+            .send(vec!["BZPOPMIN", "queue:urgent", "queue:normal", "0.05"])
+            .await?;
+        assert_eq!(res, Value::Nil);
+
+        let blocked = client.clone();
+        let blocked_pop = tokio::spawn(async move {
+            blocked
+                .send(vec!["BZPOPMAX", "queue:urgent", "queue:normal", "0"])
+                .await
+        });
+        let adder = Client::connect(LOCALHOST).await?;
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        adder
+            .send(vec!["ZADD", "queue:normal", "1", "x", "5", "y", "3", "z"])
+            .await?;
+        assert_eq!(
+            blocked_pop.await.unwrap()?,
+            Value::Array(vec![
+                Value::String(Bytes::from_static(b"queue:normal")),
+                Value::String(Bytes::from_static(b"y")),
+                Value::String(Bytes::from_static(b"5"))
+            ])
+        );
+
+        let res = client
+            .send(vec![
+                "BZMPOP",
+                "0",
+                "1",
+                "queue:normal",
+                "MIN",
+                "COUNT",
+                "5",
+            ])
+            .await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![
+                Value::String(Bytes::from_static(b"queue:normal")),
+                Value::Array(vec![
+                    Value::Array(vec![
+                        Value::String(Bytes::from_static(b"x")),
+                        Value::String(Bytes::from_static(b"1"))
+                    ]),
+                    Value::Array(vec![
+                        Value::String(Bytes::from_static(b"z")),
+                        Value::String(Bytes::from_static(b"3"))
+                    ])
+                ])
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn geo_search() -> ClientResult<()> {
+        let client = Client::connect(LOCALHOST).await?;
+        client.send(vec!["DEL", "sicily"]).await?;
+        client
+            .send(vec![
+                "GEOADD",
+                "sicily",
+                "13.361389",
+                "38.115556",
+                "Palermo",
+                "15.087269",
+                "37.502669",
+                "Catania",
+            ])
+            .await?;
+
+        let res = client
+            .send(vec!["GEODIST", "sicily", "Palermo", "Catania", "km"])
+            .await?;
+        assert_eq!(res, Value::String(Bytes::from_static(b"166.2742")));
+        let res = client
+            .send(vec![
+                "GEOSEARCH",
+                "sicily",
+                "FROMLONLAT",
+                "15",
+                "37",
+                "BYRADIUS",
+                "200",
+                "km",
+                "DESC",
+                "WITHDIST",
+            ])
+            .await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![
+                Value::Array(vec![
+                    Value::String(Bytes::from_static(b"Palermo")),
+                    Value::String(Bytes::from_static(b"190.4424"))
+                ]),
+                Value::Array(vec![
+                    Value::String(Bytes::from_static(b"Catania")),
+                    Value::String(Bytes::from_static(b"56.4413"))
+                ])
+            ])
+        );
+        let res = client
+            .send(vec![
+                "GEOSEARCH",
+                "sicily",
+                "FROMMEMBER",
+                "Palermo",
+                "BYBOX",
+                "400",
+                "100",
+                "km",
+                "COUNT",
+                "5",
+            ])
+            .await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![Value::String(Bytes::from_static(b"Palermo"))])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn geo_hash_and_store() -> ClientResult<()> {
+        let client = Client::connect(LOCALHOST).await?;
+        client.send(vec!["DEL", "stations", "nearby"]).await?;
+        client
+            .send(vec![
+                "GEOADD",
+                "stations",
+                "13.361389",
+                "38.115556",
+                "Palermo",
+                "15.087269",
+                "37.502669",
+                "Catania",
+            ])
+            .await?;
+
+        let res = client
+            .send(vec!["GEOHASH", "stations", "Palermo", "Messina"])
+            .await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![
+                Value::String(Bytes::from_static(b"sqc8b49rny0")),
+                Value::Nil
+            ])
+        );
+        let res = client
+            .send(vec![
+                "GEORADIUSBYMEMBER",
+                "stations",
+                "Catania",
+                "100",
+                "km",
+            ])
+            .await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![Value::String(Bytes::from_static(b"Catania"))])
+        );
+        let res = client
+            .send(vec![
+                "GEOSEARCHSTORE",
+                "nearby",
+                "stations",
+                "FROMLONLAT",
+                "15",
+                "37",
+                "BYRADIUS",
+                "100",
+                "km",
+                "STOREDIST",
+            ])
+            .await?;
+        assert_eq!(res, Value::Int(1));
+        let res = client
+            .send(vec!["ZRANGE", "nearby", "0", "-1", "WITHSCORES"])
+            .await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![
+                Value::String(Bytes::from_static(b"Catania")),
+                Value::String(Bytes::from_static(b"56.44125787015818"))
+            ])
+        );
+        let res = client
+            .send(vec![
+                "GEORADIUS",
+                "stations",
+                "15",
+                "37",
+                "1",
+                "km",
+                "STORE",
+                "nearby",
+            ])
+            .await?;
+        assert_eq!(res, Value::Int(0));
+        assert_eq!(client.send(vec!["ZCARD", "nearby"]).await?, Value::Int(0));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn geo_polygon_search() -> ClientResult<()> {
+        let client = Client::connect(LOCALHOST).await?;
+        client.send(vec!["DEL", "couriers"]).await?;
+        client
+            .send(vec![
+                "GEOADD",
+                "couriers",
+                "13.361389",
+                "38.115556",
+                "Palermo",
+                "15.087269",
+                "37.502669",
+                "Catania",
+            ])
+            .await?;
+
+        let res = client
+            .send(vec![
+                "GEOSEARCH",
+                "couriers",
+                "FROMLONLAT",
+                "15",
+                "37",
+                "BYPOLYGON",
+                "3",
+                "14.5",
+                "37",
+                "15.5",
+                "37",
+                "15.5",
+                "38",
+                "km",
+            ])
+            .await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![Value::String(Bytes::from_static(b"Catania"))])
+        );
+        let res = client
+            .send(vec![
+                "GEOSEARCH",
+                "couriers",
+                "FROMMEMBER",
+                "Palermo",
+                "BYPOLYGON",
+                "4",
+                "12",
+                "37",
+                "16",
+                "37",
+                "16",
+                "39",
+                "12",
+                "39",
+                "km",
+                "DESC",
+                "COUNT",
+                "1",
+                "WITHDIST",
+            ])
+            .await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![Value::Array(vec![
+                Value::String(Bytes::from_static(b"Catania")),
+                Value::String(Bytes::from_static(b"166.2742"))
+            ])])
+        );
+        assert!(
+            client
+                .send(vec![
+                    "GEOSEARCH",
+                    "couriers",
+                    "FROMMEMBER",
+                    "Palermo",
+                    "BYPOLYGON",
+                    "2",
+                    "12",
+                    "37",
+                    "16",
+                    "37",
+                    "km",
+                ])
+                .await
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_editing() -> ClientResult<()> {
+        let client = Client::connect(LOCALHOST).await?;
+        client.send(vec!["DEL", "events"]).await?;
+
+        let res = client.send(vec!["RPUSHX", "events", "a"]).await?;
+        assert_eq!(res, Value::Int(0));
+        client
+            .send(vec!["RPUSH", "events", "a", "b", "a", "c", "a"])
+            .await?;
+
+        let res = client
+            .send(vec!["LPOS", "events", "a", "RANK", "-1", "COUNT", "2"])
+            .await?;
+        assert_eq!(res, Value::Array(vec![Value::Int(4), Value::Int(2)]));
+        let res = client.send(vec!["LREM", "events", "-2", "a"]).await?;
+        assert_eq!(res, Value::Int(2));
+        let res = client
+            .send(vec!["LINSERT", "events", "AFTER", "b", "x"])
+            .await?;
+        assert_eq!(res, Value::Int(4));
+        client.send(vec!["LSET", "events", "-1", "z"]).await?;
+        client.send(vec!["LTRIM", "events", "1", "-1"]).await?;
+
+        let res = client.send(vec!["LRANGE", "events", "0", "-1"]).await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![
+                Value::String(Bytes::from_static(b"b")),
+                Value::String(Bytes::from_static(b"x")),
+                Value::String(Bytes::from_static(b"z"))
+            ])
+        );
+        assert_eq!(
+            client.send(vec!["LINDEX", "events", "-3"]).await?,
+            Value::String(Bytes::from_static(b"b"))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_move() -> ClientResult<()> {
+        let client = Client::connect(LOCALHOST).await?;
+        client
+            .send(vec!["DEL", "tasks:todo", "tasks:doing", "tasks:name"])
+            .await?;
+        client
+            .send(vec!["RPUSH", "tasks:todo", "a", "b", "c"])
+            .await?;
+
+        let res = client
+            .send(vec!["LMOVE", "tasks:todo", "tasks:doing", "LEFT", "RIGHT"])
+            .await?;
+        assert_eq!(res, Value::String(Bytes::from_static(b"a")));
+        let res = client
+            .send(vec!["RPOPLPUSH", "tasks:todo", "tasks:doing"])
+            .await?;
+        assert_eq!(res, Value::String(Bytes::from_static(b"c")));
+        // rotate the list when the source and destination are the same
+        let res = client
+            .send(vec!["LMOVE", "tasks:doing", "tasks:doing", "LEFT", "RIGHT"])
+            .await?;
+        assert_eq!(res, Value::String(Bytes::from_static(b"c")));
+        assert_eq!(
+            client
+                .send(vec!["LRANGE", "tasks:doing", "0", "-1"])
+                .await?,
+            Value::Array(vec![
+                Value::String(Bytes::from_static(b"a")),
+                Value::String(Bytes::from_static(b"c"))
+            ])
+        );
+
+        // the source isn't popped when the destination has the wrong type
+        client.send(vec!["SET", "tasks:name", "x"]).await?;
+        assert!(
+            client
+                .send(vec!["LMOVE", "tasks:todo", "tasks:name", "LEFT", "LEFT"])
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            client.send(vec!["LRANGE", "tasks:todo", "0", "-1"]).await?,
+            Value::Array(vec![Value::String(Bytes::from_static(b"b"))])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn blocking_list_move() -> ClientResult<()> {
+        let client = Client::connect(LOCALHOST).await?;
+        client
+            .send(vec!["DEL", "queue:in", "queue:mid", "queue:out"])
+            .await?;
+
+        let mover = Client::connect(LOCALHOST).await?;
+        let blocked_move = tokio::spawn(async move {
+            mover
+                .send(vec![
+                    "BLMOVE",
+                    "queue:in",
+                    "queue:mid",
+                    "RIGHT",
+                    "LEFT",
+                    "0",
+                ])
+                .await
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        // a second client waiting on the destination of the first
+        let next_mover = Client::connect(LOCALHOST).await?;
+        let next_blocked_move = tokio::spawn(async move {
+            next_mover
+                .send(vec!["BRPOPLPUSH", "queue:mid", "queue:out", "1"])
+                .await
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        client.send(vec!["RPUSH", "queue:in", "x"]).await?;
+        assert_eq!(
+            blocked_move.await.unwrap()?,
+            Value::String(Bytes::from_static(b"x"))
+        );
+        assert_eq!(
+            next_blocked_move.await.unwrap()?,
+            Value::String(Bytes::from_static(b"x"))
+        );
+        assert_eq!(client.send(vec!["LLEN", "queue:mid"]).await?, Value::Int(0));
+        assert_eq!(
+            client.send(vec!["LRANGE", "queue:out", "0", "-1"]).await?,
+            Value::Array(vec![Value::String(Bytes::from_static(b"x"))])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn multi_key_pop() -> ClientResult<()> {
+        let client = Client::connect(LOCALHOST).await?;
+        client.send(vec!["DEL", "jobs:high", "jobs:low"]).await?;
+        client
+            .send(vec!["RPUSH", "jobs:low", "a", "b", "c"])
+            .await?;
+
+        let res = client
+            .send(vec![
+                "LMPOP",
+                "2",
+                "jobs:high",
+                "jobs:low",
+                "LEFT",
+                "COUNT",
+                "2",
+            ])
+            .await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![
+                Value::String(Bytes::from_static(b"jobs:low")),
+                Value::Array(vec![
+                    Value::String(Bytes::from_static(b"a")),
+                    Value::String(Bytes::from_static(b"b"))
+                ])
+            ])
+        );
+        let res = client
+            .send(vec!["BRPOP", "jobs:high", "jobs:low", "0"])
+            .await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![
+                Value::String(Bytes::from_static(b"jobs:low")),
+                Value::String(Bytes::from_static(b"c"))
+            ])
+        );
+
+        let blocked = client.clone();
+        let blocked_pop = tokio::spawn(async move {
+            blocked
+                .send(vec!["BLMPOP", "1", "2", "jobs:high", "jobs:low", "RIGHT"])
+                .await
+        });
+        let pusher = Client::connect(LOCALHOST).await?;
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        pusher.send(vec!["RPUSH", "jobs:high", "x", "y"]).await?;
+        assert_eq!(
+            blocked_pop.await.unwrap()?,
+            Value::Array(vec![
+                Value::String(Bytes::from_static(b"jobs:high")),
+                Value::Array(vec![Value::String(Bytes::from_static(b"y"))])
+            ])
+        );
+        assert_eq!(pusher.send(vec!["LLEN", "jobs:high"]).await?, Value::Int(1));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn set_algebra() -> ClientResult<()> {
+        let client = Client::connect(LOCALHOST).await?;
+        client
+            .send(vec![
+                "DEL",
+                "tags:rust",
+                "tags:db",
+                "tags:both",
+                "tags:none",
+            ])
+            .await?;
+        client
+            .send(vec!["SADD", "tags:rust", "tokio", "serde", "sled"])
+            .await?;
+        client
+            .send(vec!["SADD", "tags:db", "sled", "redis", "serde"])
+            .await?;
+
+        let res = client
+            .send(vec!["SINTERSTORE", "tags:both", "tags:rust", "tags:db"])
+            .await?;
+        assert_eq!(res, Value::Int(2));
+        let res = client
+            .send(vec![
+                "SINTERCARD",
+                "2",
+                "tags:rust",
+                "tags:db",
+                "LIMIT",
+                "1",
+            ])
+            .await?;
+        assert_eq!(res, Value::Int(1));
+        let res = client.send(vec!["SDIFF", "tags:rust", "tags:db"]).await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![Value::String(Bytes::from_static(b"tokio"))])
+        );
+        let res = client.send(vec!["SUNION", "tags:rust", "tags:db"]).await?;
+        assert!(matches!(res, Value::Array(members) if members.len() == 4));
+
+        let res = client
+            .send(vec!["SMOVE", "tags:rust", "tags:none", "tokio"])
+            .await?;
+        assert_eq!(res, Value::Int(1));
+        let res = client
+            .send(vec!["SMISMEMBER", "tags:none", "tokio", "serde"])
+            .await?;
+        assert_eq!(res, Value::Array(vec![Value::Int(1), Value::Int(0)]));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn random_sampling() -> ClientResult<()> {
+        let client = Client::connect(LOCALHOST).await?;
+        client.send(vec!["DEL", "buckets"]).await?;
+        client.send(vec!["SADD", "buckets", "a", "b", "c"]).await?;
+
+        let res = client.send(vec!["SRANDMEMBER", "buckets", "5"]).await?;
+        assert!(matches!(res, Value::Array(members) if members.len() == 3));
+        let res = client.send(vec!["SRANDMEMBER", "buckets", "-5"]).await?;
+        assert!(matches!(res, Value::Array(members) if members.len() == 5));
+        // counts beyond half the i64 range are rejected instead of allocated
+        for command in ["SRANDMEMBER", "HRANDFIELD", "ZRANDMEMBER"] {
+            assert!(
+                client
+                    .send(vec![command, "buckets:missing", "-9223372036854775808"])
+                    .await
+                    .is_err()
+            );
+        }
+
+        let res = client.send(vec!["SPOP", "buckets", "2"]).await?;
+        assert!(matches!(res, Value::Array(members) if members.len() == 2));
+        assert_eq!(client.send(vec!["SCARD", "buckets"]).await?, Value::Int(1));
+        client.send(vec!["SPOP", "buckets"]).await?;
+        assert_eq!(client.send(vec!["SPOP", "buckets"]).await?, Value::Nil);
+        assert!(!matches!(
+            client.send(vec!["RANDOMKEY"]).await?,
+            Value::String(key) if key == "buckets"
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn hash() -> ClientResult<()> {
+        let client = Client::connect(LOCALHOST).await?;
+        let res = client
+            .send(vec!["HSET", "user:1", "name", "foo", "visits", "1"])
+            .await?;
+        assert!(matches!(res, Value::Int(_)));
+
+        let res = client
+            .send(vec!["HINCRBY", "user:1", "visits", "2"])
+            .await?;
+        assert_eq!(res, Value::Int(3));
+
+        let res = client
+            .send(vec!["HMGET", "user:1", "name", "visits", "missing"])
+            .await?;
+        assert_eq!(
+            res,
+            Value::Array(vec![
+                Value::String(Bytes::from_static(b"foo")),
+                Value::String(Bytes::from_static(b"3")),
+                Value::Nil
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn expire() -> ClientResult<()> {
+        let client = Client::connect(LOCALHOST).await?;
+        client.send(vec!["SET", "session", "abc"]).await?;
+
+        let res = client.send(vec!["EXPIRE", "session", "100", "XX"]).await?;
+        assert_eq!(res, Value::Int(0));
+        let res = client.send(vec!["EXPIRE", "session", "100", "NX"]).await?;
+        assert_eq!(res, Value::Int(1));
+        let res = client.send(vec!["EXPIRE", "session", "50", "GT"]).await?;
+        assert_eq!(res, Value::Int(0));
+        assert!(matches!(
+            client.send(vec!["PTTL", "session"]).await?,
+            Value::Int(99_000..=100_000)
+        ));
+
+        let res = client.send(vec!["PERSIST", "session"]).await?;
+        assert_eq!(res, Value::Int(1));
+        assert_eq!(client.send(vec!["TTL", "session"]).await?, Value::Int(-1));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn bitmap() -> ClientResult<()> {
+        let client = Client::connect(LOCALHOST).await?;
+        client.send(vec!["DEL", "active", "active:both"]).await?;
+
+        assert_eq!(
+            client.send(vec!["SETBIT", "active", "100", "1"]).await?,
+            Value::Int(0)
+        );
+        assert_eq!(
+            client.send(vec!["SETBIT", "active", "100", "1"]).await?,
+            Value::Int(1)
+        );
+        assert_eq!(client.send(vec!["STRLEN", "active"]).await?, Value::Int(13));
+        assert_eq!(
+            client.send(vec!["GETBIT", "active", "5000"]).await?,
+            Value::Int(0)
+        );
+
+        client.send(vec!["SET", "bits", "foobar"]).await?;
+        assert_eq!(client.send(vec!["BITCOUNT", "bits"]).await?, Value::Int(26));
+        assert_eq!(
+            client
+                .send(vec!["BITCOUNT", "bits", "5", "30", "BIT"])
+                .await?,
+            Value::Int(17)
+        );
+        assert_eq!(
+            client.send(vec!["BITPOS", "bits", "1", "2"]).await?,
+            Value::Int(17)
+        );
+
+        let res = client
+            .send(vec!["BITOP", "AND", "active:both", "active", "bits"])
+            .await?;
+        assert_eq!(res, Value::Int(13));
+        assert_eq!(
+            client.send(vec!["BITCOUNT", "active:both"]).await?,
+            Value::Int(0)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn hyperloglog() -> ClientResult<()> {
+        let client = Client::connect(LOCALHOST).await?;
+        client
+            .send(vec!["DEL", "visitors:1", "visitors:2", "visitors"])
+            .await?;
+
+        let res = client
+            .send(vec!["PFADD", "visitors:1", "a", "b", "c", "d"])
+            .await?;
+        assert_eq!(res, Value::Int(1));
+        let res = client.send(vec!["PFADD", "visitors:1", "a", "b"]).await?;
+        assert_eq!(res, Value::Int(0));
+        client
+            .send(vec!["PFADD", "visitors:2", "c", "d", "e"])
+            .await?;
+
+        assert_eq!(
+            client.send(vec!["PFCOUNT", "visitors:1"]).await?,
+            Value::Int(4)
+        );
+        assert_eq!(
+            client
+                .send(vec!["PFCOUNT", "visitors:1", "visitors:2"])
+                .await?,
+            Value::Int(5)
+        );
+        let res = client
+            .send(vec!["PFMERGE", "visitors", "visitors:1", "visitors:2"])
+            .await?;
+        assert_eq!(res, constants::OK.try_into()?);
+        assert_eq!(
+            client.send(vec!["PFCOUNT", "visitors"]).await?,
+            Value::Int(5)
+        );
+        assert_eq!(
+            client.send(vec!["TYPE", "visitors"]).await?,
+            Value::String(Bytes::from_static(b"string"))
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn pipeline() -> ClientResult<()> {
         let client = Client::connect(LOCALHOST).await?;
@@ -8,7 +8,7 @@ pub type RedisParseResult = Result<Option<(RespValueRef, usize)>, RedisParseErro
 
 /// Top-level parse function. Looks at the starting tag and parses the data accordingly.
 pub fn parse(buf: &BytesMut, pos: usize) -> RedisParseResult {
-    if buf.is_empty() {
+    if buf.len() <= pos {
         return Ok(None);
     }
 
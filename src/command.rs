@@ -9,12 +9,16 @@ use crate::{
     arguments::Arguments,
     server::Config,
     storage::{
-        geo::GeoStorage,
+        bitmap::{BitFieldOp, BitOp, BitRange, BitmapStorage},
+        geo::{GeoSearchQuery, GeoSearchWith, GeoStorage, GeoUnit},
+        hash::HashStorage,
+        hyperloglog::HyperLogLogStorage,
         list::{ListDirection, ListStorage},
-        set::SetStorage,
-        sorted_set::SortedSetStorage,
+        set::{SetOp, SetStorage},
+        sorted_set::{SortedSetStorage, ZAddOptions, ZRangeBy, ZRangeQuery, ZSetOpOptions},
         stream::StreamStorage,
-        Storage,
+        string::StringStorage,
+        Expiry, ExpiryCondition, ExpiryUpdate, SetOptions, Storage,
     },
     tasks::{Notifiers, Queues},
 };
@@ -44,23 +48,123 @@ pub enum Command {
     Set {
         key: Bytes,
         val: Bytes,
-        ttl: Option<u64>,
+        options: SetOptions,
+    },
+    SetNx {
+        key: Bytes,
+        val: Bytes,
+    },
+    MGet {
+        keys: Vec<Bytes>,
+    },
+    MSet {
+        pairs: Vec<(Bytes, Bytes)>,
+        nx: bool,
+    },
+    Append {
+        key: Bytes,
+        val: Bytes,
+    },
+    StrLen {
+        key: Bytes,
+    },
+    GetRange {
+        key: Bytes,
+        start: i64,
+        end: i64,
+    },
+    SetRange {
+        key: Bytes,
+        offset: usize,
+        val: Bytes,
+    },
+    Lcs {
+        key1: Bytes,
+        key2: Bytes,
+        len: bool,
+        idx: bool,
+        min_match_len: usize,
+        with_match_len: bool,
+    },
+    SetBit {
+        key: Bytes,
+        offset: usize,
+        bit: bool,
+    },
+    GetBit {
+        key: Bytes,
+        offset: usize,
+    },
+    BitCount {
+        key: Bytes,
+        range: Option<BitRange>,
+    },
+    BitPos {
+        key: Bytes,
+        bit: bool,
+        range: Option<BitRange>,
+    },
+    BitOp {
+        op: BitOp,
+        dest: Bytes,
+        keys: Vec<Bytes>,
+    },
+    BitField {
+        key: Bytes,
+        ops: Vec<BitFieldOp>,
+    },
+    PfAdd {
+        key: Bytes,
+        elems: Vec<Bytes>,
+    },
+    PfCount {
+        keys: Vec<Bytes>,
+    },
+    PfMerge {
+        dest: Bytes,
+        keys: Vec<Bytes>,
+    },
+    GetEx {
+        key: Bytes,
+        expiry: ExpiryUpdate,
+    },
+    GetDel {
+        key: Bytes,
     },
     Type {
         key: Bytes,
     },
     Ttl {
         key: Bytes,
+        millis: bool,
+    },
+    ExpireTime {
+        key: Bytes,
+        millis: bool,
+    },
+    Expire {
+        key: Bytes,
+        expiry: Expiry,
+        condition: Option<ExpiryCondition>,
+    },
+    Persist {
+        key: Bytes,
     },
     Del {
         keys: Vec<Bytes>,
     },
     Incr {
         key: Bytes,
+        incr: i64,
+    },
+    IncrByFloat {
+        key: Bytes,
+        incr: f64,
     },
     Keys {
         _pattern: Bytes,
     },
+    RandomKey,
     Push {
         key: Bytes,
         elems: VecDeque<Bytes>,
@@ -72,10 +176,39 @@ pub enum Command {
         count: i64,
     },
     BPop {
-        key: Bytes,
+        keys: Vec<Bytes>,
+        dir: ListDirection,
+        timeout_millis: u64,
+    },
+    LMPop {
+        keys: Vec<Bytes>,
+        dir: ListDirection,
+        count: usize,
+    },
+    BLMPop {
+        keys: Vec<Bytes>,
         dir: ListDirection,
+        count: usize,
+        timeout_millis: u64,
+    },
+    LMove {
+        src: Bytes,
+        dest: Bytes,
+        from: ListDirection,
+        to: ListDirection,
+    },
+    BLMove {
+        src: Bytes,
+        dest: Bytes,
+        from: ListDirection,
+        to: ListDirection,
         timeout_millis: u64,
     },
+    PushX {
+        key: Bytes,
+        elems: VecDeque<Bytes>,
+        dir: ListDirection,
+    },
     LLen {
         key: Bytes,
     },
@@ -84,6 +217,38 @@ pub enum Command {
         start: i64,
         stop: i64,
     },
+    LIndex {
+        key: Bytes,
+        index: i64,
+    },
+    LSet {
+        key: Bytes,
+        index: i64,
+        elem: Bytes,
+    },
+    LInsert {
+        key: Bytes,
+        before: bool,
+        pivot: Bytes,
+        elem: Bytes,
+    },
+    LRem {
+        key: Bytes,
+        count: i64,
+        elem: Bytes,
+    },
+    LTrim {
+        key: Bytes,
+        start: i64,
+        stop: i64,
+    },
+    LPos {
+        key: Bytes,
+        elem: Bytes,
+        rank: i64,
+        count: Option<usize>,
+        maxlen: usize,
+    },
     SAdd {
         key: Bytes,
         members: Vec<Bytes>,
@@ -102,13 +267,124 @@ pub enum Command {
         key: Bytes,
         member: Bytes,
     },
+    SMIsMember {
+        key: Bytes,
+        members: Vec<Bytes>,
+    },
+    SRandMember {
+        key: Bytes,
+        count: Option<i64>,
+    },
+    SPop {
+        key: Bytes,
+        count: Option<usize>,
+    },
+    SetOp {
+        op: SetOp,
+        keys: Vec<Bytes>,
+    },
+    SetOpStore {
+        op: SetOp,
+        dest: Bytes,
+        keys: Vec<Bytes>,
+    },
+    SInterCard {
+        keys: Vec<Bytes>,
+        limit: usize,
+    },
+    SMove {
+        src: Bytes,
+        dest: Bytes,
+        member: Bytes,
+    },
+    HSet {
+        key: Bytes,
+        fields: Vec<(Bytes, Bytes)>,
+    },
+    HSetNx {
+        key: Bytes,
+        field: Bytes,
+        value: Bytes,
+    },
+    HGet {
+        key: Bytes,
+        field: Bytes,
+    },
+    HMGet {
+        key: Bytes,
+        fields: Vec<Bytes>,
+    },
+    HDel {
+        key: Bytes,
+        fields: Vec<Bytes>,
+    },
+    HExists {
+        key: Bytes,
+        field: Bytes,
+    },
+    HLen {
+        key: Bytes,
+    },
+    HKeys {
+        key: Bytes,
+    },
+    HRandField {
+        key: Bytes,
+        count: Option<i64>,
+        with_values: bool,
+    },
+    HVals {
+        key: Bytes,
+    },
+    HGetAll {
+        key: Bytes,
+    },
+    HIncrBy {
+        key: Bytes,
+        field: Bytes,
+        incr: i64,
+    },
+    HIncrByFloat {
+        key: Bytes,
+        field: Bytes,
+        incr: f64,
+    },
+    HStrLen {
+        key: Bytes,
+        field: Bytes,
+    },
+    HExpire {
+        key: Bytes,
+        expiry: Expiry,
+        condition: Option<ExpiryCondition>,
+        fields: Vec<Bytes>,
+    },
+    HTtl {
+        key: Bytes,
+        fields: Vec<Bytes>,
+        millis: bool,
+    },
+    HPersist {
+        key: Bytes,
+        fields: Vec<Bytes>,
+    },
     ZAdd {
         key: Bytes,
         members: Vec<(f64, Bytes)>,
+        options: ZAddOptions,
+        /// Return the number of changed members, not only added members (`CH`)
+        ch: bool,
+    },
+    ZIncrBy {
+        key: Bytes,
+        incr: f64,
+        member: Bytes,
+        options: ZAddOptions,
     },
     ZRank {
         key: Bytes,
         member: Bytes,
+        rev: bool,
     },
     ZScore {
         key: Bytes,
@@ -116,12 +392,75 @@ pub enum Command {
     },
     ZRange {
         key: Bytes,
-        start: i64,
-        stop: i64,
+        query: ZRangeQuery,
+        with_scores: bool,
+    },
+    ZRangeStore {
+        dest: Bytes,
+        src: Bytes,
+        query: ZRangeQuery,
     },
     ZCard {
         key: Bytes,
     },
+    ZMScore {
+        key: Bytes,
+        members: Vec<Bytes>,
+    },
+    ZCount {
+        key: Bytes,
+        range: ZRangeBy,
+    },
+    ZRemRange {
+        key: Bytes,
+        range: ZRangeBy,
+    },
+    ZPop {
+        key: Bytes,
+        /// Pop the highest scores instead of the lowest
+        max: bool,
+        count: Option<usize>,
+    },
+    ZMPop {
+        keys: Vec<Bytes>,
+        /// Pop the highest scores instead of the lowest
+        max: bool,
+        count: usize,
+    },
+    BZPop {
+        keys: Vec<Bytes>,
+        /// Pop the highest score instead of the lowest
+        max: bool,
+        timeout_millis: u64,
+    },
+    BZMPop {
+        keys: Vec<Bytes>,
+        /// Pop the highest scores instead of the lowest
+        max: bool,
+        count: usize,
+        timeout_millis: u64,
+    },
+    ZSetOp {
+        op: SetOp,
+        keys: Vec<Bytes>,
+        options: ZSetOpOptions,
+        with_scores: bool,
+    },
+    ZSetOpStore {
+        op: SetOp,
+        dest: Bytes,
+        keys: Vec<Bytes>,
+        options: ZSetOpOptions,
+    },
+    ZInterCard {
+        keys: Vec<Bytes>,
+        limit: usize,
+    },
+    ZRandMember {
+        key: Bytes,
+        count: Option<i64>,
+        with_scores: bool,
+    },
     ZRem {
         key: Bytes,
         members: Vec<Bytes>,
@@ -134,15 +473,27 @@ pub enum Command {
         key: Bytes,
         members: Vec<Bytes>,
     },
+    GeoHash {
+        key: Bytes,
+        members: Vec<Bytes>,
+    },
     GeoDist {
         key: Bytes,
         member1: Bytes,
         member2: Bytes,
+        unit: GeoUnit,
     },
     GeoSearch {
         key: Bytes,
-        from: (f64, f64),
-        radius: f64,
+        query: GeoSearchQuery,
+        with: GeoSearchWith,
+    },
+    GeoSearchStore {
+        dest: Bytes,
+        src: Bytes,
+        query: GeoSearchQuery,
+        /// Score the stored members by their distance instead of their geohash
+        store_dist: bool,
     },
     XAdd {
         key: Bytes,
@@ -203,8 +554,12 @@ impl Command {
         storage: &mut (impl Storage
                   + ListStorage
                   + SetStorage
+                  + HashStorage
                   + SortedSetStorage
                   + StreamStorage
+                  + StringStorage
+                  + BitmapStorage
+                  + HyperLogLogStorage
                   + GeoStorage),
         config: &Config,
         queues: &Queues,
@@ -9,12 +9,16 @@ use tracing::warn;
 use crate::{
     server::Config,
     storage::{
-        geo::GeoStorage,
+        bitmap::{BitFieldOp, BitmapStorage},
+        geo::{GeoMatch, GeoSearchWith, GeoStorage},
+        hash::HashStorage,
+        hyperloglog::HyperLogLogStorage,
         list::ListStorage,
         set::SetStorage,
         sorted_set::SortedSetStorage,
         stream::{StreamEntry, StreamStorage},
-        Storage,
+        string::StringStorage,
+        ExpiryUpdate, SetCondition, SetOptions, Storage, StorageResult,
     },
     tasks::{Notifiers, Queues},
 };
@@ -27,8 +31,12 @@ pub fn execute_command(
     storage: &mut (impl Storage
               + ListStorage
               + SetStorage
+              + HashStorage
               + SortedSetStorage
               + StreamStorage
+              + StringStorage
+              + BitmapStorage
+              + HyperLogLogStorage
               + GeoStorage),
     config: &Config,
     queues: &Queues,
@@ -63,13 +71,192 @@ pub fn execute_command(
             Some(val) => RespValue::String(val).into(),
             None => RespValue::NilString.into(),
         },
-        Command::Set { key, val, ttl } => {
-            storage.set(key, val, ttl);
+        Command::Set { key, val, options } => {
+            let get = options.get;
+            let (is_set, previous) = storage.set(key, val, options)?;
+            if is_set {
+                notifiers.change_incr(1);
+            }
+            match (get, is_set, previous) {
+                (true, _, Some(previous)) => RespValue::String(previous).into(),
+                (false, true, _) => constants::OK.into(),
+                _ => RespValue::NilString.into(),
+            }
+        }
+        Command::SetNx { key, val } => {
+            let options = SetOptions {
+                condition: Some(SetCondition::NotExists),
+                ..Default::default()
+            };
+            let (is_set, _) = storage.set(key, val, options)?;
+            if is_set {
+                notifiers.change_incr(1);
+            }
+            RespValue::Int(is_set.into()).into()
+        }
+        Command::MGet { keys } => {
+            let values = storage
+                .mget(&keys)
+                .into_iter()
+                .map(|val| val.map_or(RespValue::NilString, RespValue::String))
+                .collect();
+            RespValue::Array(values).into()
+        }
+        Command::MSet { pairs, nx } => {
+            let num_keys = pairs.len() as i64;
+            if nx {
+                let is_set = storage.msetnx(pairs);
+                if is_set {
+                    notifiers.change_incr(num_keys);
+                }
+                RespValue::Int(is_set.into()).into()
+            } else {
+                storage.mset(pairs);
+                notifiers.change_incr(num_keys);
+                constants::OK.into()
+            }
+        }
+        Command::Append { key, val } => {
+            let len = storage.append(key, val)?;
+            notifiers.change_incr(1);
+            RespValue::Int(len).into()
+        }
+        Command::StrLen { key } => RespValue::Int(storage.strlen(&key)?).into(),
+        Command::GetRange { key, start, end } => {
+            RespValue::String(storage.getrange(&key, start, end)?).into()
+        }
+        Command::SetRange { key, offset, val } => {
+            let is_empty = val.is_empty();
+            let len = storage.setrange(key, offset, val)?;
+            if !is_empty {
+                notifiers.change_incr(1);
+            }
+            RespValue::Int(len).into()
+        }
+        Command::Lcs {
+            key1,
+            key2,
+            len,
+            idx,
+            min_match_len,
+            with_match_len,
+        } => {
+            let lcs = storage.lcs(&key1, &key2)?;
+            let lcs_len = lcs.sequence.len() as i64;
+            if idx {
+                let int = |i: usize| RespValue::Int(i as i64);
+                let matches = lcs
+                    .matches
+                    .into_iter()
+                    .filter(|m| m.len >= min_match_len)
+                    .map(|m| {
+                        let mut values = vec![
+                            RespValue::Array(vec![int(m.a.0), int(m.a.1)]),
+                            RespValue::Array(vec![int(m.b.0), int(m.b.1)]),
+                        ];
+                        if with_match_len {
+                            values.push(int(m.len));
+                        }
+                        RespValue::Array(values)
+                    })
+                    .collect();
+                RespValue::Array(vec![
+                    RespValue::String(Bytes::from_static(b"matches")),
+                    RespValue::Array(matches),
+                    RespValue::String(Bytes::from_static(b"len")),
+                    RespValue::Int(lcs_len),
+                ])
+                .into()
+            } else if len {
+                RespValue::Int(lcs_len).into()
+            } else {
+                RespValue::String(Bytes::from(lcs.sequence)).into()
+            }
+        }
+        Command::SetBit { key, offset, bit } => {
+            let previous = storage.setbit(key, offset, bit)?;
+            notifiers.change_incr(1);
+            RespValue::Int(previous).into()
+        }
+        Command::GetBit { key, offset } => RespValue::Int(storage.getbit(&key, offset)?).into(),
+        Command::BitCount { key, range } => RespValue::Int(storage.bitcount(&key, range)?).into(),
+        Command::BitPos { key, bit, range } => {
+            RespValue::Int(storage.bitpos(&key, bit, range)?).into()
+        }
+        Command::BitOp { op, dest, keys } => {
+            let len = storage.bitop(op, dest, &keys)?;
+            notifiers.change_incr(1);
+            RespValue::Int(len).into()
+        }
+        Command::BitField { key, ops } => {
+            let is_write = ops.iter().any(|op| !matches!(op, BitFieldOp::Get(_)));
+            let results = storage.bitfield(key, ops)?;
+            if is_write {
+                notifiers.change_incr(1);
+            }
+            let values = results
+                .into_iter()
+                .map(|value| value.map_or(RespValue::NilString, RespValue::Int))
+                .collect();
+            RespValue::Array(values).into()
+        }
+        Command::PfAdd { key, elems } => {
+            let is_updated = storage.pfadd(key, elems)?;
+            if is_updated {
+                notifiers.change_incr(1);
+            }
+            RespValue::Int(is_updated.into()).into()
+        }
+        Command::PfCount { keys } => RespValue::Int(storage.pfcount(&keys)?).into(),
+        Command::PfMerge { dest, keys } => {
+            storage.pfmerge(dest, &keys)?;
             notifiers.change_incr(1);
             constants::OK.into()
         }
+        Command::GetEx { key, expiry } => {
+            let val = storage.getex(&key, expiry)?;
+            if val.is_some() && !matches!(expiry, ExpiryUpdate::Keep) {
+                notifiers.change_incr(1);
+            }
+            match val {
+                Some(val) => RespValue::String(val).into(),
+                None => RespValue::NilString.into(),
+            }
+        }
+        Command::GetDel { key } => match storage.getdel(&key)? {
+            Some(val) => {
+                notifiers.change_incr(1);
+                RespValue::String(val).into()
+            }
+            None => RespValue::NilString.into(),
+        },
         Command::Type { key } => RespValue::SimpleString(storage.kind(&key)).into(),
-        Command::Ttl { key } => RespValue::Int(storage.ttl(&key)).into(),
+        Command::Ttl { key, millis } => match storage.pttl(&key) {
+            ttl if ttl >= 0 && !millis => RespValue::Int(ttl / 1000).into(),
+            ttl => RespValue::Int(ttl).into(),
+        },
+        Command::ExpireTime { key, millis } => match storage.expire_time(&key) {
+            time if time >= 0 && !millis => RespValue::Int(time / 1000).into(),
+            time => RespValue::Int(time).into(),
+        },
+        Command::Expire {
+            key,
+            expiry,
+            condition,
+        } => {
+            let updated = storage.expire(&key, expiry.unix_millis(), condition);
+            if updated {
+                notifiers.change_incr(1);
+            }
+            RespValue::Int(updated.into()).into()
+        }
+        Command::Persist { key } => {
+            let updated = storage.persist(&key);
+            if updated {
+                notifiers.change_incr(1);
+            }
+            RespValue::Int(updated.into()).into()
+        }
         Command::Del { keys } => {
             let mut count = 0;
             for key in keys {
@@ -80,14 +267,23 @@ pub fn execute_command(
             notifiers.change_incr(count);
             RespValue::Int(count).into()
         }
-        Command::Incr { key } => {
-            let incr = storage.incr(key)?;
+        Command::Incr { key, incr } => {
+            let value = storage.incrby(key, incr)?;
+            notifiers.change_incr(1);
+            RespValue::Int(value).into()
+        }
+        Command::IncrByFloat { key, incr } => {
+            let value = storage.incrbyfloat(key, incr)?;
             notifiers.change_incr(1);
-            RespValue::Int(incr).into()
+            RespValue::String(Bytes::from(value.to_string())).into()
         }
         Command::Keys { .. } => {
             RespValue::Array(storage.keys().into_iter().map(RespValue::String).collect()).into()
         }
+        Command::RandomKey => match storage.random_key() {
+            Some(key) => RespValue::String(key).into(),
+            None => RespValue::NilString.into(),
+        },
         Command::Push { key, elems, dir } => {
             let len = storage.push(key.clone(), elems, dir)?;
             notifiers.change_incr(1);
@@ -106,49 +302,147 @@ pub fn execute_command(
             None => RespValue::NilString.into(),
         },
         Command::BPop {
-            key,
+            keys,
             dir,
             timeout_millis,
-        } => {
-            if let Some(mut elems) = storage.pop(&key, dir, 1) {
+        } => match storage.mpop(&keys, dir, 1)? {
+            Some((key, mut elems)) => {
                 notifiers.change_incr(1);
                 RespValue::Array(vec![
                     RespValue::String(key),
                     RespValue::String(elems.pop().expect("should have 1 item")),
                 ])
                 .into()
-            } else {
-                let key_response = key.clone();
+            }
+            None => {
                 let (tx, rx) = oneshot::channel();
-                queues.bpop_push(key, dir, tx);
-                let block_response = if timeout_millis == 0 {
-                    rx.map_ok(|bytes| {
-                        Ok(RespValue::Array(vec![
-                            RespValue::String(key_response),
-                            RespValue::String(bytes),
-                        ]))
-                    })
-                    .boxed()
-                } else {
-                    tokio::time::timeout(Duration::from_millis(timeout_millis), rx)
-                        .map(|res| match res {
-                            Ok(Ok(bytes)) => Ok(Ok(RespValue::Array(vec![
-                                RespValue::String(key_response), // POP response
-                                RespValue::String(bytes),
-                            ]))),
-                            Ok(Err(e)) => Err(e), // Receiver disconnected
-                            Err(_) => Ok(Ok(RespValue::NilArray)), // Timeout
-                        })
-                        .boxed()
-                };
-                CommandResponse::Block(block_response)
+                queues.bpop_push(keys, dir, 1, tx);
+                bpop_response(rx, timeout_millis, RespValue::NilArray, |key, mut elems| {
+                    RespValue::Array(vec![
+                        RespValue::String(key),
+                        RespValue::String(elems.pop().expect("should have 1 item")),
+                    ])
+                })
+            }
+        },
+        Command::LMPop { keys, dir, count } => match storage.mpop(&keys, dir, count)? {
+            Some((key, elems)) => {
+                notifiers.change_incr(1);
+                format_mpop(key, elems).into()
+            }
+            None => RespValue::NilArray.into(),
+        },
+        Command::BLMPop {
+            keys,
+            dir,
+            count,
+            timeout_millis,
+        } => match storage.mpop(&keys, dir, count)? {
+            Some((key, elems)) => {
+                notifiers.change_incr(1);
+                format_mpop(key, elems).into()
+            }
+            None => {
+                let (tx, rx) = oneshot::channel();
+                queues.bpop_push(keys, dir, count, tx);
+                bpop_response(rx, timeout_millis, RespValue::NilArray, format_mpop)
+            }
+        },
+        Command::LMove {
+            src,
+            dest,
+            from,
+            to,
+        } => match storage.lmove(&src, dest.clone(), from, to)? {
+            Some(elem) => {
+                notifiers.change_incr(1);
+                notifiers.bpop_notify(dest); // notify blocking POP task
+                RespValue::String(elem).into()
+            }
+            None => RespValue::NilString.into(),
+        },
+        Command::BLMove {
+            src,
+            dest,
+            from,
+            to,
+            timeout_millis,
+        } => match storage.lmove(&src, dest.clone(), from, to)? {
+            Some(elem) => {
+                notifiers.change_incr(1);
+                notifiers.bpop_notify(dest); // notify blocking POP task
+                RespValue::String(elem).into()
+            }
+            None => {
+                let (tx, rx) = oneshot::channel();
+                queues.bpop_move_push(src, from, (dest, to), tx);
+                bpop_response(rx, timeout_millis, RespValue::NilString, |_, mut elems| {
+                    RespValue::String(elems.pop().expect("should have 1 item"))
+                })
+            }
+        },
+        Command::PushX { key, elems, dir } => {
+            let len = storage.pushx(&key, elems, dir)?;
+            if len > 0 {
+                notifiers.change_incr(1);
+                notifiers.bpop_notify(key); // notify blocking POP task
             }
+            RespValue::Int(len).into()
         }
         Command::LLen { key } => RespValue::Int(storage.llen(&key)).into(),
         Command::LRange { key, start, stop } => {
             let elems = storage.lrange(&key, start, stop);
             RespValue::Array(elems.into_iter().map(RespValue::String).collect()).into()
         }
+        Command::LIndex { key, index } => match storage.lindex(&key, index)? {
+            Some(elem) => RespValue::String(elem).into(),
+            None => RespValue::NilString.into(),
+        },
+        Command::LSet { key, index, elem } => {
+            storage.lset(&key, index, elem)?;
+            notifiers.change_incr(1);
+            constants::OK.into()
+        }
+        Command::LInsert {
+            key,
+            before,
+            pivot,
+            elem,
+        } => {
+            let len = storage.linsert(&key, before, &pivot, elem)?;
+            if len > 0 {
+                notifiers.change_incr(1);
+            }
+            RespValue::Int(len).into()
+        }
+        Command::LRem { key, count, elem } => {
+            let num = storage.lrem(&key, count, &elem)?;
+            if num > 0 {
+                notifiers.change_incr(1);
+            }
+            RespValue::Int(num).into()
+        }
+        Command::LTrim { key, start, stop } => {
+            storage.ltrim(&key, start, stop)?;
+            notifiers.change_incr(1);
+            constants::OK.into()
+        }
+        Command::LPos {
+            key,
+            elem,
+            rank,
+            count,
+            maxlen,
+        } => {
+            let indexes = storage.lpos(&key, &elem, rank, count.unwrap_or(1), maxlen)?;
+            match count {
+                Some(_) => RespValue::Array(indexes.into_iter().map(RespValue::Int).collect()),
+                None => indexes
+                    .first()
+                    .map_or(RespValue::NilString, |idx| RespValue::Int(*idx)),
+            }
+            .into()
+        }
         Command::SAdd { key, members } => {
             let num = storage.sadd(key, members)?;
             if num > 0 {
@@ -172,25 +466,318 @@ pub fn execute_command(
             true => RespValue::Int(1).into(),
             false => RespValue::Int(0).into(),
         },
-        Command::ZAdd { key, members } => {
-            let num = storage.zadd(key, members)?;
+        Command::SMIsMember { key, members } => {
+            let results = storage.smismember(&key, &members)?;
+            let results = results
+                .into_iter()
+                .map(|is_member| RespValue::Int(is_member.into()));
+            RespValue::Array(results.collect()).into()
+        }
+        Command::SRandMember { key, count } => {
+            let members = storage.srandmember(&key, count.unwrap_or(1))?;
+            format_random_sample(members, count.is_some()).into()
+        }
+        Command::SPop { key, count } => {
+            let members = storage.spop(&key, count.unwrap_or(1))?;
+            if !members.is_empty() {
+                notifiers.change_incr(1);
+            }
+            format_random_sample(members, count.is_some()).into()
+        }
+        Command::SetOp { op, keys } => {
+            let members = storage.setop(op, &keys)?;
+            RespValue::Array(members.into_iter().map(RespValue::String).collect()).into()
+        }
+        Command::SetOpStore { op, dest, keys } => {
+            let len = storage.setop_store(op, dest, &keys)?;
+            notifiers.change_incr(1);
+            RespValue::Int(len).into()
+        }
+        Command::SInterCard { keys, limit } => {
+            RespValue::Int(storage.sintercard(&keys, limit)?).into()
+        }
+        Command::SMove { src, dest, member } => match storage.smove(&src, dest, member)? {
+            true => {
+                notifiers.change_incr(1);
+                RespValue::Int(1).into()
+            }
+            false => RespValue::Int(0).into(),
+        },
+        Command::HSet { key, fields } => {
+            let num = storage.hset(key, fields)?;
+            notifiers.change_incr(1);
+            RespValue::Int(num).into()
+        }
+        Command::HSetNx { key, field, value } => match storage.hsetnx(key, field, value)? {
+            true => {
+                notifiers.change_incr(1);
+                RespValue::Int(1).into()
+            }
+            false => RespValue::Int(0).into(),
+        },
+        Command::HGet { key, field } => match storage.hget(&key, &field)? {
+            Some(value) => RespValue::String(value).into(),
+            None => RespValue::NilString.into(),
+        },
+        Command::HMGet { key, fields } => {
+            let values = storage.hmget(&key, fields)?;
+            let values = values
+                .into_iter()
+                .map(|value| value.map_or(RespValue::NilString, RespValue::String))
+                .collect();
+            RespValue::Array(values).into()
+        }
+        Command::HDel { key, fields } => {
+            let num = storage.hdel(&key, fields)?;
             if num > 0 {
                 notifiers.change_incr(1);
             }
             RespValue::Int(num).into()
         }
-        Command::ZRank { key, member } => match storage.zrank(&key, member)? {
-            Some(rank) => RespValue::Int(rank).into(),
+        Command::HExists { key, field } => match storage.hexists(&key, &field)? {
+            true => RespValue::Int(1).into(),
+            false => RespValue::Int(0).into(),
+        },
+        Command::HLen { key } => RespValue::Int(storage.hlen(&key)?).into(),
+        Command::HKeys { key } => {
+            let fields = storage.hkeys(&key)?;
+            RespValue::Array(fields.into_iter().map(RespValue::String).collect()).into()
+        }
+        Command::HVals { key } => {
+            let values = storage.hvals(&key)?;
+            RespValue::Array(values.into_iter().map(RespValue::String).collect()).into()
+        }
+        Command::HRandField {
+            key,
+            count,
+            with_values,
+        } => {
+            let entries = storage.hrandfield(&key, count.unwrap_or(1))?;
+            match with_values {
+                true => RespValue::Array(
+                    entries
+                        .into_iter()
+                        .flat_map(|(field, value)| {
+                            [RespValue::String(field), RespValue::String(value)]
+                        })
+                        .collect(),
+                ),
+                false => {
+                    let fields = entries.into_iter().map(|(field, _)| field).collect();
+                    format_random_sample(fields, count.is_some())
+                }
+            }
+            .into()
+        }
+        Command::HGetAll { key } => {
+            let entries = storage.hgetall(&key)?;
+            let values = entries
+                .into_iter()
+                .flat_map(|(field, value)| [RespValue::String(field), RespValue::String(value)])
+                .collect();
+            RespValue::Array(values).into()
+        }
+        Command::HIncrBy { key, field, incr } => {
+            let value = storage.hincrby(key, field, incr)?;
+            notifiers.change_incr(1);
+            RespValue::Int(value).into()
+        }
+        Command::HIncrByFloat { key, field, incr } => {
+            let value = storage.hincrbyfloat(key, field, incr)?;
+            notifiers.change_incr(1);
+            RespValue::String(Bytes::from(value.to_string())).into()
+        }
+        Command::HStrLen { key, field } => RespValue::Int(storage.hstrlen(&key, &field)?).into(),
+        Command::HExpire {
+            key,
+            expiry,
+            condition,
+            fields,
+        } => {
+            let results = storage.hexpire(&key, expiry.unix_millis(), condition, fields)?;
+            if results.iter().any(|res| *res > 0) {
+                notifiers.change_incr(1);
+            }
+            RespValue::Array(results.into_iter().map(RespValue::Int).collect()).into()
+        }
+        Command::HTtl {
+            key,
+            fields,
+            millis,
+        } => {
+            let ttls = storage.hpttl(&key, fields)?;
+            let values = ttls
+                .into_iter()
+                .map(|ttl| match ttl {
+                    ttl if ttl >= 0 && !millis => RespValue::Int(ttl / 1000),
+                    ttl => RespValue::Int(ttl),
+                })
+                .collect();
+            RespValue::Array(values).into()
+        }
+        Command::HPersist { key, fields } => {
+            let results = storage.hpersist(&key, fields)?;
+            if results.iter().any(|res| *res > 0) {
+                notifiers.change_incr(1);
+            }
+            RespValue::Array(results.into_iter().map(RespValue::Int).collect()).into()
+        }
+        Command::ZAdd {
+            key,
+            members,
+            options,
+            ch,
+        } => {
+            let (num_added, num_updated) = storage.zadd(key.clone(), members, options)?;
+            if num_added + num_updated > 0 {
+                notifiers.change_incr(1);
+            }
+            if num_added > 0 {
+                notifiers.bzpop_notify(key); // notify blocking ZPOP task
+            }
+            match ch {
+                true => RespValue::Int(num_added + num_updated).into(),
+                false => RespValue::Int(num_added).into(),
+            }
+        }
+        Command::ZIncrBy {
+            key,
+            incr,
+            member,
+            options,
+        } => match storage.zincrby(key.clone(), incr, member, options)? {
+            Some(score) => {
+                notifiers.change_incr(1);
+                notifiers.bzpop_notify(key); // notify blocking ZPOP task
+                RespValue::String(Bytes::from(score.to_string())).into()
+            }
             None => RespValue::NilString.into(),
         },
+        Command::ZRank { key, member, rev } => {
+            let rank = match rev {
+                true => storage.zrevrank(&key, member)?,
+                false => storage.zrank(&key, member)?,
+            };
+            match rank {
+                Some(rank) => RespValue::Int(rank).into(),
+                None => RespValue::NilString.into(),
+            }
+        }
         Command::ZScore { key, member } => match storage.zscore(&key, &member)? {
             Some(score) => RespValue::String(Bytes::from(score.to_string())).into(),
             None => RespValue::NilString.into(),
         },
         Command::ZCard { key } => RespValue::Int(storage.zcard(&key)?).into(),
-        Command::ZRange { key, start, stop } => {
-            let members = storage.zrange(&key, start, stop)?;
-            RespValue::Array(members.into_iter().map(RespValue::String).collect()).into()
+        Command::ZRange {
+            key,
+            query,
+            with_scores,
+        } => format_scored_members(storage.zrange(&key, &query)?, with_scores).into(),
+        Command::ZRangeStore { dest, src, query } => {
+            let len = storage.zrangestore(dest.clone(), &src, &query)?;
+            notifiers.change_incr(1);
+            if len > 0 {
+                notifiers.bzpop_notify(dest); // notify blocking ZPOP task
+            }
+            RespValue::Int(len).into()
+        }
+        Command::ZMScore { key, members } => {
+            let scores = storage.zmscore(&key, &members)?;
+            let scores = scores.into_iter().map(|score| match score {
+                Some(score) => RespValue::String(Bytes::from(score.to_string())),
+                None => RespValue::NilString,
+            });
+            RespValue::Array(scores.collect()).into()
+        }
+        Command::ZCount { key, range } => RespValue::Int(storage.zcount(&key, range)?).into(),
+        Command::ZRemRange { key, range } => {
+            let num = storage.zremrange(&key, range)?;
+            if num > 0 {
+                notifiers.change_incr(1);
+            }
+            RespValue::Int(num).into()
+        }
+        Command::ZPop { key, max, count } => {
+            let popped = storage.zpop(&key, max, count.unwrap_or(1))?;
+            if !popped.is_empty() {
+                notifiers.change_incr(1);
+            }
+            format_scored_members(popped, true).into()
+        }
+        Command::ZMPop { keys, max, count } => match storage.zmpop(&keys, max, count)? {
+            Some((key, popped)) => {
+                notifiers.change_incr(1);
+                format_zmpop(key, popped).into()
+            }
+            None => RespValue::NilArray.into(),
+        },
+        Command::BZPop {
+            keys,
+            max,
+            timeout_millis,
+        } => match storage.zmpop(&keys, max, 1)? {
+            Some((key, popped)) => {
+                notifiers.change_incr(1);
+                format_bzpop(key, popped).into()
+            }
+            None => {
+                let (tx, rx) = oneshot::channel();
+                queues.bzpop_push(keys, max, 1, tx);
+                bpop_response(rx, timeout_millis, RespValue::NilArray, format_bzpop)
+            }
+        },
+        Command::BZMPop {
+            keys,
+            max,
+            count,
+            timeout_millis,
+        } => match storage.zmpop(&keys, max, count)? {
+            Some((key, popped)) => {
+                notifiers.change_incr(1);
+                format_zmpop(key, popped).into()
+            }
+            None => {
+                let (tx, rx) = oneshot::channel();
+                queues.bzpop_push(keys, max, count, tx);
+                bpop_response(rx, timeout_millis, RespValue::NilArray, format_zmpop)
+            }
+        },
+        Command::ZSetOp {
+            op,
+            keys,
+            options,
+            with_scores,
+        } => format_scored_members(storage.zsetop(op, &keys, &options)?, with_scores).into(),
+        Command::ZSetOpStore {
+            op,
+            dest,
+            keys,
+            options,
+        } => {
+            let len = storage.zsetop_store(op, dest.clone(), &keys, &options)?;
+            notifiers.change_incr(1);
+            if len > 0 {
+                notifiers.bzpop_notify(dest); // notify blocking ZPOP task
+            }
+            RespValue::Int(len).into()
+        }
+        Command::ZInterCard { keys, limit } => {
+            RespValue::Int(storage.zintercard(&keys, limit)?).into()
+        }
+        Command::ZRandMember {
+            key,
+            count,
+            with_scores,
+        } => {
+            let entries = storage.zrandmember(&key, count.unwrap_or(1))?;
+            match with_scores {
+                true => format_scored_members(entries, true),
+                false => {
+                    let members = entries.into_iter().map(|(member, _)| member).collect();
+                    format_random_sample(members, count.is_some())
+                }
+            }
+            .into()
         }
         Command::ZRem { key, members } => {
             let num = storage.zrem(&key, members)?;
@@ -200,9 +787,10 @@ pub fn execute_command(
             RespValue::Int(num).into()
         }
         Command::GeoAdd { key, members } => {
-            let num = storage.geoadd(key, members)?;
+            let num = storage.geoadd(key.clone(), members)?;
             if num > 0 {
                 notifiers.change_incr(1);
+                notifiers.bzpop_notify(key); // notify blocking ZPOP task
             }
             RespValue::Int(num).into()
         }
@@ -220,17 +808,40 @@ pub fn execute_command(
                 .collect();
             RespValue::Array(values).into()
         }
+        Command::GeoHash { key, members } => {
+            let hashes = storage.geohash(&key, &members)?;
+            let values = hashes.into_iter().map(|hash| match hash {
+                Some(hash) => RespValue::String(Bytes::from(hash)),
+                None => RespValue::NilString,
+            });
+            RespValue::Array(values.collect()).into()
+        }
         Command::GeoDist {
             key,
             member1,
             member2,
-        } => match storage.geodist(&key, &member1, &member2)? {
-            Some(dist) => RespValue::String(Bytes::from(dist.to_string())).into(),
+            unit,
+        } => match storage.geodist(&key, &member1, &member2, unit)? {
+            Some(dist) => format_geo_dist(dist).into(),
             None => RespValue::NilString.into(),
         },
-        Command::GeoSearch { key, from, radius } => {
-            let members = storage.geosearch(&key, from, radius)?;
-            RespValue::Array(members.into_iter().map(RespValue::String).collect()).into()
+        Command::GeoSearch { key, query, with } => {
+            let matches = storage.geosearch(&key, &query)?;
+            let values = matches.into_iter().map(|m| format_geo_match(m, with));
+            RespValue::Array(values.collect()).into()
+        }
+        Command::GeoSearchStore {
+            dest,
+            src,
+            query,
+            store_dist,
+        } => {
+            let len = storage.geosearchstore(dest.clone(), &src, &query, store_dist)?;
+            notifiers.change_incr(1);
+            if len > 0 {
+                notifiers.bzpop_notify(dest); // notify blocking ZPOP task
+            }
+            RespValue::Int(len).into()
         }
         Command::XAdd { key, id, data } => {
             let id = storage.xadd(key.clone(), id, data)?;
@@ -304,6 +915,114 @@ pub fn execute_command(
     Ok(command_response)
 }
 
+/// Wait for a blocked list or sorted set pop to be served by its blocking pop task, formatting
+/// the popped key and elements into the response. Responds with `timeout_response` once the timeout is
+/// reached (`0` to block forever).
+fn bpop_response<T: Send + 'static>(
+    rx: oneshot::Receiver<StorageResult<(Bytes, Vec<T>)>>,
+    timeout_millis: u64,
+    timeout_response: RespValue,
+    format: impl FnOnce(Bytes, Vec<T>) -> RespValue + Send + 'static,
+) -> CommandResponse {
+    let format = |(key, elems)| format(key, elems);
+    let block_response = if timeout_millis == 0 {
+        rx.map_ok(|res| res.map(format)).boxed()
+    } else {
+        tokio::time::timeout(Duration::from_millis(timeout_millis), rx)
+            .map(|res| match res {
+                Ok(Ok(res)) => Ok(res.map(format)), // POP response
+                Ok(Err(e)) => Err(e),               // Receiver disconnected
+                Err(_) => Ok(Ok(timeout_response)), // Timeout
+            })
+            .boxed()
+    };
+    CommandResponse::Block(block_response)
+}
+
+/// Format sorted set members, interleaved with their scores if requested
+fn format_scored_members(entries: Vec<(Bytes, f64)>, with_scores: bool) -> RespValue {
+    let values = entries.into_iter().flat_map(|(member, score)| {
+        let score = with_scores.then(|| RespValue::String(Bytes::from(score.to_string())));
+        std::iter::once(RespValue::String(member)).chain(score)
+    });
+    RespValue::Array(values.collect())
+}
+
+/// Format a geo distance with 4 decimal places
+fn format_geo_dist(dist: f64) -> RespValue {
+    RespValue::String(Bytes::from(format!("{dist:.4}")))
+}
+
+/// Format a GEOSEARCH match as the member, or as an array of the member followed by the
+/// requested distance, hash, and coordinates
+fn format_geo_match(geo_match: GeoMatch, with: GeoSearchWith) -> RespValue {
+    let member = RespValue::String(geo_match.member);
+    if !(with.dist || with.hash || with.coord) {
+        return member;
+    }
+    let mut values = vec![member];
+    if with.dist {
+        values.push(format_geo_dist(geo_match.dist));
+    }
+    if with.hash {
+        values.push(RespValue::Int(
+            geo_match.hash.try_into().unwrap_or_default(),
+        ));
+    }
+    if with.coord {
+        let (lon, lat) = geo_match.coord;
+        values.push(RespValue::Array(vec![
+            RespValue::String(Bytes::from(lon.to_string())),
+            RespValue::String(Bytes::from(lat.to_string())),
+        ]));
+    }
+    RespValue::Array(values)
+}
+
+/// Format the key and popped member of BZPOPMIN/BZPOPMAX, followed by its score
+fn format_bzpop(key: Bytes, mut popped: Vec<(Bytes, f64)>) -> RespValue {
+    let (member, score) = popped.pop().expect("should have 1 item");
+    RespValue::Array(vec![
+        RespValue::String(key),
+        RespValue::String(member),
+        RespValue::String(Bytes::from(score.to_string())),
+    ])
+}
+
+/// Format the key and popped members of ZMPOP/BZMPOP, as pairs of members and scores
+fn format_zmpop(key: Bytes, popped: Vec<(Bytes, f64)>) -> RespValue {
+    let pairs = popped.into_iter().map(|(member, score)| {
+        RespValue::Array(vec![
+            RespValue::String(member),
+            RespValue::String(Bytes::from(score.to_string())),
+        ])
+    });
+    RespValue::Array(vec![
+        RespValue::String(key),
+        RespValue::Array(pairs.collect()),
+    ])
+}
+
+/// Format the members returned by a random sampling command: an array if a count was given,
+/// otherwise a single member
+fn format_random_sample(members: Vec<Bytes>, has_count: bool) -> RespValue {
+    match has_count {
+        true => RespValue::Array(members.into_iter().map(RespValue::String).collect()),
+        false => members
+            .into_iter()
+            .next()
+            .map_or(RespValue::NilString, RespValue::String),
+    }
+}
+
+/// Format the key and popped elements of LMPOP/BLMPOP
+fn format_mpop(key: Bytes, elems: Vec<Bytes>) -> RespValue {
+    RespValue::Array(vec![
+        RespValue::String(key),
+        RespValue::Array(elems.into_iter().map(RespValue::String).collect()),
+    ])
+}
+
 fn format_stream_id((ms, seq): (u64, u64)) -> Bytes {
     let (ms_str, seq_str) = (ms.to_string(), seq.to_string());
     let mut bytes = BytesMut::with_capacity(ms_str.len() + seq_str.len() + 1);
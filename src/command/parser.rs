@@ -1,13 +1,24 @@
-use std::collections::VecDeque;
+use std::{collections::VecDeque, ops::Bound};
 
 use anyhow::bail;
+use bytes::Bytes;
+use tinikeyval_protocol::RespValue;
 
 use super::Command;
 use crate::{
     arguments::Arguments,
     storage::{
-        geo::{validate_lat, validate_lon},
+        bitmap::{BitField, BitFieldOp, BitFieldOverflow, BitOp, BitRange, BitRangeUnit},
+        geo::{
+            validate_lat, validate_lon, GeoOrder, GeoOrigin, GeoSearchQuery, GeoSearchWith,
+            GeoShape, GeoUnit,
+        },
         list::ListDirection,
+        set::SetOp,
+        sorted_set::{
+            ZAddComparison, ZAddOptions, ZAggregate, ZRangeBy, ZRangeQuery, ZSetOpOptions,
+        },
+        Expiry, ExpiryCondition, ExpiryUpdate, SetCondition, SetOptions,
     },
 };
 
@@ -38,20 +49,290 @@ pub fn parse_command(mut args: Arguments) -> anyhow::Result<Command> {
         "SET" => {
             let key = args.pop("key")?;
             let val = args.pop("value")?;
-            let ex: Option<u64> = args.pop_parse_optional_named("EX")?;
-            let px: Option<u64> = args.pop_parse_optional_named("PX")?;
-            let ttl = match (ex, px) {
-                (None, None) => None,
-                (Some(ex), None) => Some(ex * 1000),
-                (None, Some(px)) => Some(px),
-                (Some(_), Some(_)) => bail!("can't provide both EX and PX"),
+            let mut options = SetOptions::default();
+            while let Some(arg) = args.pop_optional() {
+                let is_expiry_set = !matches!(options.expiry, ExpiryUpdate::Clear);
+                match arg.to_ascii_uppercase().as_slice() {
+                    b"NX" | b"XX" if options.condition.is_some() => bail!("ERR syntax error"),
+                    b"NX" => options.condition = Some(SetCondition::NotExists),
+                    b"XX" => options.condition = Some(SetCondition::Exists),
+                    b"GET" => options.get = true,
+                    _ if is_expiry_set => bail!("ERR syntax error"),
+                    b"KEEPTTL" => options.expiry = ExpiryUpdate::Keep,
+                    option => match parse_expiry_option(option, &mut args)? {
+                        Some(expiry) => options.expiry = ExpiryUpdate::Set(expiry),
+                        None => bail!("ERR syntax error"),
+                    },
+                }
+            }
+            Command::Set { key, val, options }
+        }
+        "SETNX" => Command::SetNx {
+            key: args.pop("key")?,
+            val: args.pop("value")?,
+        },
+        "SETEX" | "PSETEX" => {
+            let key = args.pop("key")?;
+            let time: i64 = args.pop_parse("time")?;
+            let ttl_millis = match args.command() {
+                "SETEX" => time.checked_mul(1000),
+                _ => Some(time),
+            };
+            let Some(ttl_millis) = ttl_millis.filter(|ttl| *ttl > 0) else {
+                bail!(
+                    "ERR invalid expire time in '{}' command",
+                    args.command().to_lowercase()
+                );
+            };
+            let val = args.pop("value")?;
+            let options = SetOptions {
+                expiry: ExpiryUpdate::Set(Expiry::In(ttl_millis)),
+                ..Default::default()
+            };
+            Command::Set { key, val, options }
+        }
+        "GETSET" => {
+            let key = args.pop("key")?;
+            let val = args.pop("value")?;
+            let options = SetOptions {
+                get: true,
+                ..Default::default()
+            };
+            Command::Set { key, val, options }
+        }
+        "MGET" => {
+            let mut keys = vec![args.pop("key")?];
+            while let Some(key) = args.pop_optional() {
+                keys.push(key);
+            }
+            Command::MGet { keys }
+        }
+        "MSET" | "MSETNX" => {
+            let mut pairs = vec![(args.pop("key")?, args.pop("value")?)];
+            while let Some(key) = args.pop_optional() {
+                pairs.push((key, args.pop("value")?));
+            }
+            Command::MSet {
+                pairs,
+                nx: args.command() == "MSETNX",
+            }
+        }
+        "APPEND" => Command::Append {
+            key: args.pop("key")?,
+            val: args.pop("value")?,
+        },
+        "STRLEN" => Command::StrLen {
+            key: args.pop("key")?,
+        },
+        "GETRANGE" => Command::GetRange {
+            key: args.pop("key")?,
+            start: args.pop_parse("start")?,
+            end: args.pop_parse("end")?,
+        },
+        "SETRANGE" => {
+            let key = args.pop("key")?;
+            let offset: i64 = args.pop_parse("offset")?;
+            let Ok(offset) = usize::try_from(offset) else {
+                bail!("ERR offset is out of range");
+            };
+            let val = args.pop("value")?;
+            Command::SetRange { key, offset, val }
+        }
+        "LCS" => {
+            let key1 = args.pop("key1")?;
+            let key2 = args.pop("key2")?;
+            let (mut len, mut idx, mut min_match_len, mut with_match_len) =
+                (false, false, 0, false);
+            while let Some(arg) = args.pop_optional() {
+                match arg.to_ascii_uppercase().as_slice() {
+                    b"LEN" => len = true,
+                    b"IDX" => idx = true,
+                    b"MINMATCHLEN" => min_match_len = args.pop_parse::<i64>("len")?.max(0) as usize,
+                    b"WITHMATCHLEN" => with_match_len = true,
+                    _ => bail!("ERR syntax error"),
+                }
+            }
+            if len && idx {
+                bail!("ERR If you want both the length and indexes, please just use IDX.");
+            }
+            Command::Lcs {
+                key1,
+                key2,
+                len,
+                idx,
+                min_match_len,
+                with_match_len,
+            }
+        }
+        "SETBIT" => {
+            let key = args.pop("key")?;
+            let offset = parse_bit_offset(&mut args)?;
+            let bit = match args.pop("value")?.as_ref() {
+                b"0" => false,
+                b"1" => true,
+                _ => bail!("ERR bit is not an integer or out of range"),
             };
-            Command::Set { key, val, ttl }
+            Command::SetBit { key, offset, bit }
         }
+        "GETBIT" => {
+            let key = args.pop("key")?;
+            let offset = parse_bit_offset(&mut args)?;
+            Command::GetBit { key, offset }
+        }
+        "BITCOUNT" => {
+            let key = args.pop("key")?;
+            let range = parse_bit_range(&mut args, true)?;
+            Command::BitCount { key, range }
+        }
+        "BITPOS" => {
+            let key = args.pop("key")?;
+            let bit = match args.pop("bit")?.as_ref() {
+                b"0" => false,
+                b"1" => true,
+                _ => bail!("ERR The bit argument must be 1 or 0."),
+            };
+            let range = parse_bit_range(&mut args, false)?;
+            Command::BitPos { key, bit, range }
+        }
+        "BITOP" => {
+            let op = match args.pop("operation")?.to_ascii_uppercase().as_slice() {
+                b"AND" => BitOp::And,
+                b"OR" => BitOp::Or,
+                b"XOR" => BitOp::Xor,
+                b"NOT" => BitOp::Not,
+                b"DIFF" => BitOp::Diff,
+                _ => bail!("ERR syntax error"),
+            };
+            let dest = args.pop("destkey")?;
+            let mut keys = vec![args.pop("key")?];
+            while let Some(key) = args.pop_optional() {
+                keys.push(key);
+            }
+            match op {
+                BitOp::Not if keys.len() != 1 => {
+                    bail!("ERR BITOP NOT must be called with a single source key.")
+                }
+                BitOp::Diff if keys.len() < 2 => {
+                    bail!("ERR BITOP DIFF must be called with at least two source keys.")
+                }
+                _ => {}
+            }
+            Command::BitOp { op, dest, keys }
+        }
+        "BITFIELD" | "BITFIELD_RO" => {
+            let key = args.pop("key")?;
+            let is_read_only = args.command() == "BITFIELD_RO";
+            let mut ops = Vec::new();
+            let mut overflow = BitFieldOverflow::default();
+            while let Some(arg) = args.pop_optional() {
+                match arg.to_ascii_uppercase().as_slice() {
+                    b"GET" => ops.push(BitFieldOp::Get(parse_bit_field(&mut args)?)),
+                    b"SET" | b"INCRBY" | b"OVERFLOW" if is_read_only => {
+                        bail!("ERR BITFIELD_RO only supports the GET subcommand")
+                    }
+                    b"SET" => {
+                        let field = parse_bit_field(&mut args)?;
+                        let value = args.pop_parse("value")?;
+                        ops.push(BitFieldOp::Set(field, value, overflow));
+                    }
+                    b"INCRBY" => {
+                        let field = parse_bit_field(&mut args)?;
+                        let incr = args.pop_parse("increment")?;
+                        ops.push(BitFieldOp::IncrBy(field, incr, overflow));
+                    }
+                    b"OVERFLOW" => {
+                        overflow = match args.pop("overflow")?.to_ascii_uppercase().as_slice() {
+                            b"WRAP" => BitFieldOverflow::Wrap,
+                            b"SAT" => BitFieldOverflow::Sat,
+                            b"FAIL" => BitFieldOverflow::Fail,
+                            _ => bail!("ERR Invalid OVERFLOW type specified"),
+                        }
+                    }
+                    _ => bail!("ERR syntax error"),
+                }
+            }
+            Command::BitField { key, ops }
+        }
+        "PFADD" => {
+            let key = args.pop("key")?;
+            let mut elems = Vec::new();
+            while let Some(elem) = args.pop_optional() {
+                elems.push(elem);
+            }
+            Command::PfAdd { key, elems }
+        }
+        "PFCOUNT" => {
+            let mut keys = vec![args.pop("key")?];
+            while let Some(key) = args.pop_optional() {
+                keys.push(key);
+            }
+            Command::PfCount { keys }
+        }
+        "PFMERGE" => {
+            let dest = args.pop("destkey")?;
+            let mut keys = Vec::new();
+            while let Some(key) = args.pop_optional() {
+                keys.push(key);
+            }
+            Command::PfMerge { dest, keys }
+        }
+        "GETEX" => {
+            let key = args.pop("key")?;
+            let expiry = match args.pop_optional() {
+                None => ExpiryUpdate::Keep,
+                Some(arg) if arg.eq_ignore_ascii_case(b"PERSIST") => ExpiryUpdate::Clear,
+                Some(arg) => match parse_expiry_option(&arg.to_ascii_uppercase(), &mut args)? {
+                    Some(expiry) => ExpiryUpdate::Set(expiry),
+                    None => bail!("ERR syntax error"),
+                },
+            };
+            Command::GetEx { key, expiry }
+        }
+        "GETDEL" => Command::GetDel {
+            key: args.pop("key")?,
+        },
         "TYPE" => Command::Type {
             key: args.pop("key")?,
         },
-        "TTL" => Command::Ttl {
+        "TTL" | "PTTL" => Command::Ttl {
+            key: args.pop("key")?,
+            millis: args.command() == "PTTL",
+        },
+        "EXPIRETIME" | "PEXPIRETIME" => Command::ExpireTime {
+            key: args.pop("key")?,
+            millis: args.command() == "PEXPIRETIME",
+        },
+        "EXPIRE" | "PEXPIRE" | "EXPIREAT" | "PEXPIREAT" => {
+            let key = args.pop("key")?;
+            let time: i64 = args.pop_parse("time")?;
+            let millis = match args.command() {
+                "EXPIRE" | "EXPIREAT" => time.checked_mul(1000),
+                _ => Some(time),
+            };
+            let Some(millis) = millis else {
+                bail!(
+                    "ERR invalid expire time in '{}' command",
+                    args.command().to_lowercase()
+                );
+            };
+            let expiry = match args.command() {
+                "EXPIRE" | "PEXPIRE" => Expiry::In(millis),
+                _ => Expiry::At(millis),
+            };
+            let condition = match args.pop_optional() {
+                Some(arg) => match parse_expiry_condition(&arg) {
+                    Some(condition) => Some(condition),
+                    None => bail!("ERR Unsupported option {}", String::from_utf8_lossy(&arg)),
+                },
+                None => None,
+            };
+            Command::Expire {
+                key,
+                expiry,
+                condition,
+            }
+        }
+        "PERSIST" => Command::Persist {
             key: args.pop("key")?,
         },
         "DEL" => {
@@ -61,9 +342,30 @@ pub fn parse_command(mut args: Arguments) -> anyhow::Result<Command> {
             }
             Command::Del { keys }
         }
-        "INCR" => Command::Incr {
+        "INCR" | "DECR" => Command::Incr {
             key: args.pop("key")?,
+            incr: if args.command() == "INCR" { 1 } else { -1 },
         },
+        "INCRBY" | "DECRBY" => {
+            let key = args.pop("key")?;
+            let incr: i64 = args.pop_parse("increment")?;
+            let incr = match args.command() {
+                "INCRBY" => incr,
+                _ => match incr.checked_neg() {
+                    Some(decr) => decr,
+                    None => bail!("ERR decrement would overflow"),
+                },
+            };
+            Command::Incr { key, incr }
+        }
+        "INCRBYFLOAT" => {
+            let key = args.pop("key")?;
+            let incr: f64 = args.pop_parse("increment")?;
+            if !incr.is_finite() {
+                bail!("ERR value is NaN or Infinity");
+            }
+            Command::IncrByFloat { key, incr }
+        }
         "KEYS" => {
             let _pattern = args.pop("pattern")?;
             if _pattern.as_ref() != b"*" {
@@ -71,6 +373,7 @@ pub fn parse_command(mut args: Arguments) -> anyhow::Result<Command> {
             }
             Command::Keys { _pattern }
         }
+        "RANDOMKEY" => Command::RandomKey,
         "RPUSH" | "LPUSH" => {
             let key = args.pop("key")?;
             let elem = args.pop("element")?;
@@ -96,20 +399,92 @@ pub fn parse_command(mut args: Arguments) -> anyhow::Result<Command> {
             Command::Pop { key, dir, count }
         }
         "BRPOP" | "BLPOP" => {
-            let key = args.pop("key")?;
-            let timeout = args.pop_parse::<f32>("timeout")?;
-            let timeout_millis = (timeout * 1000.0).round() as u64;
+            let mut keys = vec![args.pop("key")?];
+            while args.remaining().len() > 1 {
+                keys.push(args.pop("key")?);
+            }
+            let timeout_millis = parse_block_timeout(&mut args)?;
             let dir = match args.command() {
                 "BRPOP" => ListDirection::Right,
                 "BLPOP" => ListDirection::Left,
                 _ => unreachable!(),
             };
             Command::BPop {
-                key,
+                keys,
                 dir,
                 timeout_millis,
             }
         }
+        "LMPOP" | "BLMPOP" => {
+            let timeout_millis = match args.command() {
+                "BLMPOP" => Some(parse_block_timeout(&mut args)?),
+                _ => None,
+            };
+            let keys = parse_numkeys(&mut args)?;
+            let dir = parse_list_direction(&args.pop("LEFT|RIGHT")?)?;
+            let count = match args.pop_optional() {
+                Some(arg) if arg.eq_ignore_ascii_case(b"COUNT") => parse_count(&mut args)?,
+                Some(_) => bail!("ERR syntax error"),
+                None => 1,
+            };
+            match timeout_millis {
+                Some(timeout_millis) => Command::BLMPop {
+                    keys,
+                    dir,
+                    count,
+                    timeout_millis,
+                },
+                None => Command::LMPop { keys, dir, count },
+            }
+        }
+        "LMOVE" | "BLMOVE" => {
+            let src = args.pop("source")?;
+            let dest = args.pop("destination")?;
+            let from = parse_list_direction(&args.pop("wherefrom")?)?;
+            let to = parse_list_direction(&args.pop("whereto")?)?;
+            match args.command() {
+                "LMOVE" => Command::LMove {
+                    src,
+                    dest,
+                    from,
+                    to,
+                },
+                "BLMOVE" => Command::BLMove {
+                    src,
+                    dest,
+                    from,
+                    to,
+                    timeout_millis: parse_block_timeout(&mut args)?,
+                },
+                _ => unreachable!(),
+            }
+        }
+        "RPOPLPUSH" => Command::LMove {
+            src: args.pop("source")?,
+            dest: args.pop("destination")?,
+            from: ListDirection::Right,
+            to: ListDirection::Left,
+        },
+        "BRPOPLPUSH" => Command::BLMove {
+            src: args.pop("source")?,
+            dest: args.pop("destination")?,
+            from: ListDirection::Right,
+            to: ListDirection::Left,
+            timeout_millis: parse_block_timeout(&mut args)?,
+        },
+        "RPUSHX" | "LPUSHX" => {
+            let key = args.pop("key")?;
+            let mut elems: VecDeque<_> = vec![args.pop("element")?].into();
+            while let Some(elem) = args.pop_optional() {
+                elems.push_back(elem);
+            }
+            let dir = match args.command() {
+                "RPUSHX" => ListDirection::Right,
+                "LPUSHX" => ListDirection::Left,
+                _ => unreachable!(),
+            };
+            Command::PushX { key, elems, dir }
+        }
         "LLEN" => Command::LLen {
             key: args.pop("key")?,
         },
@@ -119,6 +494,76 @@ pub fn parse_command(mut args: Arguments) -> anyhow::Result<Command> {
             let stop = args.pop_parse("stop index")?;
             Command::LRange { key, start, stop }
         }
+        "LINDEX" => {
+            let key = args.pop("key")?;
+            let index = args.pop_parse("index")?;
+            Command::LIndex { key, index }
+        }
+        "LSET" => {
+            let key = args.pop("key")?;
+            let index = args.pop_parse("index")?;
+            let elem = args.pop("element")?;
+            Command::LSet { key, index, elem }
+        }
+        "LINSERT" => {
+            let key = args.pop("key")?;
+            let before = match args.pop("BEFORE | AFTER")?.to_ascii_uppercase().as_slice() {
+                b"BEFORE" => true,
+                b"AFTER" => false,
+                _ => bail!("ERR syntax error"),
+            };
+            let pivot = args.pop("pivot")?;
+            let elem = args.pop("element")?;
+            Command::LInsert {
+                key,
+                before,
+                pivot,
+                elem,
+            }
+        }
+        "LREM" => {
+            let key = args.pop("key")?;
+            let count = args.pop_parse("count")?;
+            let elem = args.pop("element")?;
+            Command::LRem { key, count, elem }
+        }
+        "LTRIM" => {
+            let key = args.pop("key")?;
+            let start = args.pop_parse("start index")?;
+            let stop = args.pop_parse("stop index")?;
+            Command::LTrim { key, start, stop }
+        }
+        "LPOS" => {
+            let key = args.pop("key")?;
+            let elem = args.pop("element")?;
+            let (mut rank, mut count, mut maxlen) = (1, None, 0);
+            while let Some(arg) = args.pop_optional() {
+                match arg.to_ascii_uppercase().as_slice() {
+                    b"RANK" => {
+                        rank = args.pop_parse("rank")?;
+                        if rank == 0 || rank == i64::MIN {
+                            bail!("ERR RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the end of the list");
+                        }
+                    }
+                    b"COUNT" => match usize::try_from(args.pop_parse::<i64>("count")?) {
+                        Ok(num) => count = Some(num),
+                        Err(_) => bail!("ERR COUNT can't be negative"),
+                    },
+                    b"MAXLEN" => match usize::try_from(args.pop_parse::<i64>("maxlen")?) {
+                        Ok(len) => maxlen = len,
+                        Err(_) => bail!("ERR MAXLEN can't be negative"),
+                    },
+                    _ => bail!("ERR syntax error"),
+                }
+            }
+            Command::LPos {
+                key,
+                elem,
+                rank,
+                count,
+                maxlen,
+            }
+        }
         "SADD" | "SREM" => {
             let key = args.pop("key")?;
             let mut members = vec![args.pop("member")?];
@@ -142,20 +587,387 @@ pub fn parse_command(mut args: Arguments) -> anyhow::Result<Command> {
             let member = args.pop("member")?;
             Command::SIsMember { key, member }
         }
+        "SRANDMEMBER" => Command::SRandMember {
+            key: args.pop("key")?,
+            count: parse_random_count(&mut args)?,
+        },
+        "SPOP" => {
+            let key = args.pop("key")?;
+            let count = match args.pop_parse_optional::<i64>()? {
+                Some(count) => match count.try_into() {
+                    Ok(count) => Some(count),
+                    Err(_) => bail!("ERR value is out of range, must be positive"),
+                },
+                None => None,
+            };
+            Command::SPop { key, count }
+        }
+        "SMISMEMBER" => {
+            let key = args.pop("key")?;
+            let mut members = vec![args.pop("member")?];
+            while let Some(member) = args.pop_optional() {
+                members.push(member);
+            }
+            Command::SMIsMember { key, members }
+        }
+        "SINTER" | "SUNION" | "SDIFF" | "SINTERSTORE" | "SUNIONSTORE" | "SDIFFSTORE" => {
+            let (op, is_store) = match args.command() {
+                "SINTER" => (SetOp::Inter, false),
+                "SUNION" => (SetOp::Union, false),
+                "SDIFF" => (SetOp::Diff, false),
+                "SINTERSTORE" => (SetOp::Inter, true),
+                "SUNIONSTORE" => (SetOp::Union, true),
+                "SDIFFSTORE" => (SetOp::Diff, true),
+                _ => unreachable!(),
+            };
+            let dest = if is_store {
+                Some(args.pop("destination")?)
+            } else {
+                None
+            };
+            let mut keys = vec![args.pop("key")?];
+            while let Some(key) = args.pop_optional() {
+                keys.push(key);
+            }
+            match dest {
+                Some(dest) => Command::SetOpStore { op, dest, keys },
+                None => Command::SetOp { op, keys },
+            }
+        }
+        "SINTERCARD" | "ZINTERCARD" => {
+            let keys = parse_numkeys(&mut args)?;
+            let limit = parse_card_limit(&mut args)?;
+            match args.command() {
+                "SINTERCARD" => Command::SInterCard { keys, limit },
+                _ => Command::ZInterCard { keys, limit },
+            }
+        }
+        "SMOVE" => Command::SMove {
+            src: args.pop("source")?,
+            dest: args.pop("destination")?,
+            member: args.pop("member")?,
+        },
+        "HSET" => {
+            let key = args.pop("key")?;
+            let mut fields = vec![(args.pop("field")?, args.pop("value")?)];
+            while let Some(field) = args.pop_optional() {
+                fields.push((field, args.pop("value")?));
+            }
+            Command::HSet { key, fields }
+        }
+        "HSETNX" => {
+            let key = args.pop("key")?;
+            let field = args.pop("field")?;
+            let value = args.pop("value")?;
+            Command::HSetNx { key, field, value }
+        }
+        "HGET" | "HEXISTS" | "HSTRLEN" => {
+            let key = args.pop("key")?;
+            let field = args.pop("field")?;
+            match args.command() {
+                "HGET" => Command::HGet { key, field },
+                "HEXISTS" => Command::HExists { key, field },
+                "HSTRLEN" => Command::HStrLen { key, field },
+                _ => unreachable!(),
+            }
+        }
+        "HMGET" | "HDEL" => {
+            let key = args.pop("key")?;
+            let mut fields = vec![args.pop("field")?];
+            while let Some(field) = args.pop_optional() {
+                fields.push(field);
+            }
+            match args.command() {
+                "HMGET" => Command::HMGet { key, fields },
+                "HDEL" => Command::HDel { key, fields },
+                _ => unreachable!(),
+            }
+        }
+        "HLEN" => Command::HLen {
+            key: args.pop("key")?,
+        },
+        "HKEYS" => Command::HKeys {
+            key: args.pop("key")?,
+        },
+        "HVALS" => Command::HVals {
+            key: args.pop("key")?,
+        },
+        "HRANDFIELD" | "ZRANDMEMBER" => {
+            let key = args.pop("key")?;
+            let count = parse_random_count(&mut args)?;
+            let with_option: &[u8] = match args.command() {
+                "HRANDFIELD" => b"WITHVALUES",
+                _ => b"WITHSCORES",
+            };
+            let with = match args.pop_optional() {
+                Some(arg) if arg.eq_ignore_ascii_case(with_option) => true,
+                Some(_) => bail!("ERR syntax error"),
+                None => false,
+            };
+            match args.command() {
+                "HRANDFIELD" => Command::HRandField {
+                    key,
+                    count,
+                    with_values: with,
+                },
+                _ => Command::ZRandMember {
+                    key,
+                    count,
+                    with_scores: with,
+                },
+            }
+        }
+        "HGETALL" => Command::HGetAll {
+            key: args.pop("key")?,
+        },
+        "HINCRBY" => {
+            let key = args.pop("key")?;
+            let field = args.pop("field")?;
+            let incr = args.pop_parse("increment")?;
+            Command::HIncrBy { key, field, incr }
+        }
+        "HINCRBYFLOAT" => {
+            let key = args.pop("key")?;
+            let field = args.pop("field")?;
+            let incr: f64 = args.pop_parse("increment")?;
+            if !incr.is_finite() {
+                bail!("ERR value is NaN or Infinity");
+            }
+            Command::HIncrByFloat { key, field, incr }
+        }
+        "HEXPIRE" | "HPEXPIRE" | "HEXPIREAT" | "HPEXPIREAT" => {
+            let key = args.pop("key")?;
+            let time: u64 = args.pop_parse("time")?;
+            if time > 1 << 48 {
+                bail!("ERR invalid expire time, must be >= 0 and <= 2^48");
+            }
+            let expiry = match args.command() {
+                "HEXPIRE" => Expiry::In(time as i64 * 1000),
+                "HPEXPIRE" => Expiry::In(time as i64),
+                "HEXPIREAT" => Expiry::At(time as i64 * 1000),
+                "HPEXPIREAT" => Expiry::At(time as i64),
+                _ => unreachable!(),
+            };
+            let arg = args.pop("FIELDS")?;
+            let condition = parse_expiry_condition(&arg);
+            if condition.is_none() && !arg.eq_ignore_ascii_case(b"FIELDS") {
+                bail!("ERR Mandatory argument FIELDS is missing or not at the right position");
+            }
+            let fields = parse_fields(&mut args, condition.is_none())?;
+            Command::HExpire {
+                key,
+                expiry,
+                condition,
+                fields,
+            }
+        }
+        "HTTL" | "HPTTL" | "HPERSIST" => {
+            let key = args.pop("key")?;
+            let fields = parse_fields(&mut args, false)?;
+            match args.command() {
+                "HTTL" => Command::HTtl {
+                    key,
+                    fields,
+                    millis: false,
+                },
+                "HPTTL" => Command::HTtl {
+                    key,
+                    fields,
+                    millis: true,
+                },
+                "HPERSIST" => Command::HPersist { key, fields },
+                _ => unreachable!(),
+            }
+        }
         "ZADD" => {
             let key = args.pop("key")?;
-            let mut members = vec![(args.pop_parse("score")?, args.pop("member")?)];
-            while let (Some(score), Some(member)) =
-                (args.pop_parse_optional()?, args.pop_optional())
-            {
-                members.push((score, member));
+            let (mut nx, mut xx, mut gt, mut lt, mut ch, mut incr) = Default::default();
+            while let Some(arg) = args.remaining().front().and_then(|arg| arg.as_bytes()) {
+                match arg.to_ascii_uppercase().as_slice() {
+                    b"NX" => nx = true,
+                    b"XX" => xx = true,
+                    b"GT" => gt = true,
+                    b"LT" => lt = true,
+                    b"CH" => ch = true,
+                    b"INCR" => incr = true,
+                    _ => break,
+                }
+                args.pop("option")?;
+            }
+            if nx && xx {
+                bail!("ERR XX and NX options at the same time are not compatible");
+            }
+            if (gt && lt) || (nx && (gt || lt)) {
+                bail!("ERR GT, LT, and/or NX options at the same time are not compatible");
+            }
+            let options = ZAddOptions {
+                condition: match (nx, xx) {
+                    (true, _) => Some(SetCondition::NotExists),
+                    (_, true) => Some(SetCondition::Exists),
+                    _ => None,
+                },
+                comparison: match (gt, lt) {
+                    (true, _) => Some(ZAddComparison::GreaterThan),
+                    (_, true) => Some(ZAddComparison::LessThan),
+                    _ => None,
+                },
+            };
+
+            let mut members = vec![(parse_score(&mut args)?, args.pop("member")?)];
+            while !args.remaining().is_empty() {
+                members.push((parse_score(&mut args)?, args.pop("member")?));
+            }
+            match incr {
+                true => {
+                    let Ok([(incr, member)]) = <[_; 1]>::try_from(members) else {
+                        bail!("ERR INCR option supports a single increment-element pair");
+                    };
+                    Command::ZIncrBy {
+                        key,
+                        incr,
+                        member,
+                        options,
+                    }
+                }
+                false => Command::ZAdd {
+                    key,
+                    members,
+                    options,
+                    ch,
+                },
+            }
+        }
+        "ZUNION" | "ZINTER" | "ZDIFF" | "ZUNIONSTORE" | "ZINTERSTORE" | "ZDIFFSTORE" => {
+            let (op, is_store) = match args.command() {
+                "ZUNION" => (SetOp::Union, false),
+                "ZINTER" => (SetOp::Inter, false),
+                "ZDIFF" => (SetOp::Diff, false),
+                "ZUNIONSTORE" => (SetOp::Union, true),
+                "ZINTERSTORE" => (SetOp::Inter, true),
+                "ZDIFFSTORE" => (SetOp::Diff, true),
+                _ => unreachable!(),
+            };
+            let dest = match is_store {
+                true => Some(args.pop("destination")?),
+                false => None,
+            };
+            let keys = parse_numkeys(&mut args)?;
+            let is_diff = matches!(op, SetOp::Diff);
+            let mut options = ZSetOpOptions::default();
+            let mut with_scores = false;
+            while let Some(arg) = args.pop_optional() {
+                match arg.to_ascii_uppercase().as_slice() {
+                    b"WEIGHTS" if !is_diff => {
+                        let weights = (0..keys.len())
+                            .map(|_| parse_weight(&mut args))
+                            .collect::<anyhow::Result<_>>()?;
+                        options.weights = Some(weights);
+                    }
+                    b"AGGREGATE" if !is_diff => {
+                        options.aggregate =
+                            match args.pop("aggregate")?.to_ascii_uppercase().as_slice() {
+                                b"SUM" => ZAggregate::Sum,
+                                b"MIN" => ZAggregate::Min,
+                                b"MAX" => ZAggregate::Max,
+                                _ => bail!("ERR syntax error"),
+                            }
+                    }
+                    b"WITHSCORES" if !is_store => with_scores = true,
+                    _ => bail!("ERR syntax error"),
+                }
+            }
+            match dest {
+                Some(dest) => Command::ZSetOpStore {
+                    op,
+                    dest,
+                    keys,
+                    options,
+                },
+                None => Command::ZSetOp {
+                    op,
+                    keys,
+                    options,
+                    with_scores,
+                },
+            }
+        }
+        "ZMSCORE" => {
+            let key = args.pop("key")?;
+            let mut members = vec![args.pop("member")?];
+            while let Some(member) = args.pop_optional() {
+                members.push(member);
+            }
+            Command::ZMScore { key, members }
+        }
+        "ZCOUNT" | "ZLEXCOUNT" | "ZREMRANGEBYSCORE" | "ZREMRANGEBYLEX" | "ZREMRANGEBYRANK" => {
+            let key = args.pop("key")?;
+            let (min, max) = (args.pop("min")?, args.pop("max")?);
+            let range = match args.command() {
+                "ZCOUNT" | "ZREMRANGEBYSCORE" => parse_score_range(&min, &max)?,
+                "ZLEXCOUNT" | "ZREMRANGEBYLEX" => parse_lex_range(min, max)?,
+                _ => parse_rank_range(&min, &max)?,
+            };
+            match args.command() {
+                "ZCOUNT" | "ZLEXCOUNT" => Command::ZCount { key, range },
+                _ => Command::ZRemRange { key, range },
             }
-            Command::ZAdd { key, members }
         }
-        "ZRANK" => {
+        "ZPOPMIN" | "ZPOPMAX" => {
+            let key = args.pop("key")?;
+            let count = match args.pop_parse_optional::<i64>()? {
+                Some(count) => match count.try_into() {
+                    Ok(count) => Some(count),
+                    Err(_) => bail!("ERR value is out of range, must be positive"),
+                },
+                None => None,
+            };
+            let max = args.command() == "ZPOPMAX";
+            Command::ZPop { key, max, count }
+        }
+        "BZPOPMIN" | "BZPOPMAX" => {
+            let mut keys = vec![args.pop("key")?];
+            while args.remaining().len() > 1 {
+                keys.push(args.pop("key")?);
+            }
+            Command::BZPop {
+                keys,
+                max: args.command() == "BZPOPMAX",
+                timeout_millis: parse_block_timeout(&mut args)?,
+            }
+        }
+        "ZMPOP" | "BZMPOP" => {
+            let timeout_millis = match args.command() {
+                "BZMPOP" => Some(parse_block_timeout(&mut args)?),
+                _ => None,
+            };
+            let keys = parse_numkeys(&mut args)?;
+            let max = parse_min_max(&args.pop("MIN|MAX")?)?;
+            let count = match args.pop_optional() {
+                Some(arg) if arg.eq_ignore_ascii_case(b"COUNT") => parse_count(&mut args)?,
+                Some(_) => bail!("ERR syntax error"),
+                None => 1,
+            };
+            match timeout_millis {
+                Some(timeout_millis) => Command::BZMPop {
+                    keys,
+                    max,
+                    count,
+                    timeout_millis,
+                },
+                None => Command::ZMPop { keys, max, count },
+            }
+        }
+        "ZINCRBY" => Command::ZIncrBy {
+            key: args.pop("key")?,
+            incr: parse_score(&mut args)?,
+            member: args.pop("member")?,
+            options: ZAddOptions::default(),
+        },
+        "ZRANK" | "ZREVRANK" => {
             let key = args.pop("key")?;
             let member = args.pop("member")?;
-            Command::ZRank { key, member }
+            let rev = args.command() == "ZREVRANK";
+            Command::ZRank { key, member, rev }
         }
         "ZSCORE" => {
             let key = args.pop("key")?;
@@ -165,11 +977,26 @@ pub fn parse_command(mut args: Arguments) -> anyhow::Result<Command> {
         "ZCARD" => Command::ZCard {
             key: args.pop("key")?,
         },
-        "ZRANGE" => {
+        "ZRANGE" | "ZRANGESTORE" | "ZREVRANGE" | "ZRANGEBYSCORE" | "ZREVRANGEBYSCORE"
+        | "ZRANGEBYLEX" | "ZREVRANGEBYLEX" => {
+            let dest = match args.command() {
+                "ZRANGESTORE" => Some(args.pop("dst")?),
+                _ => None,
+            };
             let key = args.pop("key")?;
-            let start = args.pop_parse("start index")?;
-            let stop = args.pop_parse("stop index")?;
-            Command::ZRange { key, start, stop }
+            let (query, with_scores) = parse_zrange_query(&mut args, dest.is_some())?;
+            match dest {
+                Some(dest) => Command::ZRangeStore {
+                    dest,
+                    src: key,
+                    query,
+                },
+                None => Command::ZRange {
+                    key,
+                    query,
+                    with_scores,
+                },
+            }
         }
         "ZREM" => {
             let key = args.pop("key")?;
@@ -217,23 +1044,48 @@ pub fn parse_command(mut args: Arguments) -> anyhow::Result<Command> {
             let key = args.pop("key")?;
             let member1 = args.pop("member 1")?;
             let member2 = args.pop("member 2")?;
+            let unit = match args.pop_optional() {
+                Some(unit) => parse_geo_unit(&unit)?,
+                None => GeoUnit::default(),
+            };
+            if !args.remaining().is_empty() {
+                bail!("ERR syntax error");
+            }
             Command::GeoDist {
                 key,
                 member1,
                 member2,
+                unit,
             }
         }
-        "GEOSEARCH" => {
+        "GEOHASH" => {
             let key = args.pop("key")?;
-            let _fromlonlat = args.pop("FROMLONLAT")?;
-            let from = (
-                args.pop_parse("from longitude")?,
-                args.pop_parse("from latitude")?,
-            );
-            let _byradius = args.pop("BYRADIUS")?;
-            let radius = args.pop_parse("radius")?;
-            let _unit = args.pop("radius unit")?;
-            Command::GeoSearch { key, from, radius }
+            let mut members = Vec::new();
+            while let Some(member) = args.pop_optional() {
+                members.push(member);
+            }
+            Command::GeoHash { key, members }
+        }
+        "GEOSEARCH"
+        | "GEORADIUS"
+        | "GEORADIUS_RO"
+        | "GEORADIUSBYMEMBER"
+        | "GEORADIUSBYMEMBER_RO"
+        | "GEOSEARCHSTORE" => {
+            let dest = match args.command() {
+                "GEOSEARCHSTORE" => Some(args.pop("destination")?),
+                _ => None,
+            };
+            let key = args.pop("key")?;
+            match parse_geosearch_query(&mut args, dest)? {
+                (query, _, Some((dest, store_dist))) => Command::GeoSearchStore {
+                    dest,
+                    src: key,
+                    query,
+                    store_dist,
+                },
+                (query, with, None) => Command::GeoSearch { key, query, with },
+            }
         }
         "XADD" => {
             let key = args.pop("key")?;
@@ -303,3 +1155,477 @@ pub fn parse_command(mut args: Arguments) -> anyhow::Result<Command> {
 
     Ok(command)
 }
+
+/// Parse a `LEFT` or `RIGHT` list direction argument
+fn parse_list_direction(arg: &[u8]) -> anyhow::Result<ListDirection> {
+    match arg.to_ascii_uppercase().as_slice() {
+        b"LEFT" => Ok(ListDirection::Left),
+        b"RIGHT" => Ok(ListDirection::Right),
+        _ => bail!("ERR syntax error"),
+    }
+}
+
+/// Parse the range and options of ZRANGE (or one of its legacy variants, which set the type
+/// of range and the order with the command name). Returns the query and whether scores were
+/// requested with `WITHSCORES`.
+fn parse_zrange_query(args: &mut Arguments, is_store: bool) -> anyhow::Result<(ZRangeQuery, bool)> {
+    let start = args.pop("start")?;
+    let stop = args.pop("stop")?;
+
+    let command = args.command();
+    let is_legacy = !matches!(command, "ZRANGE" | "ZRANGESTORE");
+    let mut by_score = command.ends_with("BYSCORE");
+    let mut by_lex = command.ends_with("BYLEX");
+    let mut rev = command.starts_with("ZREV");
+    let mut limit = None;
+    let mut with_scores = false;
+    while let Some(arg) = args.pop_optional() {
+        match arg.to_ascii_uppercase().as_slice() {
+            b"BYSCORE" if !is_legacy && !by_lex => by_score = true,
+            b"BYLEX" if !is_legacy && !by_score => by_lex = true,
+            b"REV" if !is_legacy => rev = true,
+            b"LIMIT" => limit = Some((args.pop_parse("offset")?, args.pop_parse("count")?)),
+            b"WITHSCORES" if !is_store => with_scores = true,
+            _ => bail!("ERR syntax error"),
+        }
+    }
+    if limit.is_some() && !by_score && !by_lex {
+        bail!(
+            "ERR syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+        );
+    }
+    if with_scores && by_lex {
+        bail!("ERR syntax error, WITHSCORES not supported in combination with BYLEX");
+    }
+
+    let by = if by_score || by_lex {
+        // score and lex ranges are given from max to min when reversed
+        let (min, max) = match rev {
+            true => (stop, start),
+            false => (start, stop),
+        };
+        match by_score {
+            true => parse_score_range(&min, &max)?,
+            false => parse_lex_range(min, max)?,
+        }
+    } else {
+        parse_rank_range(&start, &stop)?
+    };
+
+    Ok((ZRangeQuery { by, rev, limit }, with_scores))
+}
+
+/// Parse a sorted set score or increment
+fn parse_score(args: &mut Arguments) -> anyhow::Result<f64> {
+    let arg = args.pop("score")?;
+    match std::str::from_utf8(&arg)
+        .ok()
+        .and_then(|s| s.parse::<f64>().ok())
+    {
+        Some(score) if !score.is_nan() => Ok(score),
+        _ => bail!("ERR value is not a valid float"),
+    }
+}
+
+/// Parse a weight of the `WEIGHTS` option
+fn parse_weight(args: &mut Arguments) -> anyhow::Result<f64> {
+    let arg = args.pop("weight")?;
+    match std::str::from_utf8(&arg)
+        .ok()
+        .and_then(|w| w.parse::<f64>().ok())
+    {
+        Some(weight) if !weight.is_nan() => Ok(weight),
+        _ => bail!("ERR weight value is not a float"),
+    }
+}
+
+/// Parse the start and stop of a sorted set rank range
+fn parse_rank_range(start: &[u8], stop: &[u8]) -> anyhow::Result<ZRangeBy> {
+    let parse_rank = |arg: &[u8]| {
+        std::str::from_utf8(arg)
+            .ok()
+            .and_then(|rank| rank.parse().ok())
+            .ok_or_else(|| anyhow::anyhow!("ERR value is not an integer or out of range"))
+    };
+    Ok(ZRangeBy::Rank(parse_rank(start)?, parse_rank(stop)?))
+}
+
+/// Parse the minimum and maximum of a sorted set score range
+fn parse_score_range(min: &[u8], max: &[u8]) -> anyhow::Result<ZRangeBy> {
+    Ok(ZRangeBy::Score(
+        parse_score_bound(min)?,
+        parse_score_bound(max)?,
+    ))
+}
+
+/// Parse the minimum and maximum of a sorted set lexicographical range
+fn parse_lex_range(min: Bytes, max: Bytes) -> anyhow::Result<ZRangeBy> {
+    Ok(match (min.as_ref(), max.as_ref()) {
+        // nothing is above the maximum or below the minimum
+        (b"+", _) | (_, b"-") => {
+            ZRangeBy::Lex(Bound::Excluded(Bytes::new()), Bound::Excluded(Bytes::new()))
+        }
+        _ => ZRangeBy::Lex(parse_lex_bound(min)?, parse_lex_bound(max)?),
+    })
+}
+
+/// Parse a score bound of a sorted set range, which is exclusive if prefixed with `(`
+fn parse_score_bound(arg: &[u8]) -> anyhow::Result<Bound<f64>> {
+    let (arg, is_exclusive) = match arg.strip_prefix(b"(") {
+        Some(arg) => (arg, true),
+        None => (arg, false),
+    };
+    let Some(score) = std::str::from_utf8(arg)
+        .ok()
+        .and_then(|score| score.parse::<f64>().ok())
+        .filter(|score| !score.is_nan())
+    else {
+        bail!("ERR min or max is not a float");
+    };
+    Ok(match is_exclusive {
+        true => Bound::Excluded(score),
+        false => Bound::Included(score),
+    })
+}
+
+/// Parse a lexicographical bound of a sorted set range: `[` or `(` followed by the member for
+/// an inclusive or exclusive bound, or `-` or `+` for the minimum or maximum
+fn parse_lex_bound(arg: Bytes) -> anyhow::Result<Bound<Bytes>> {
+    match arg.first() {
+        Some(b'-' | b'+') if arg.len() == 1 => Ok(Bound::Unbounded),
+        Some(b'[') => Ok(Bound::Included(arg.slice(1..))),
+        Some(b'(') => Ok(Bound::Excluded(arg.slice(1..))),
+        _ => bail!("ERR min or max not valid string range item"),
+    }
+}
+
+/// Parse the `numkeys key [key ...]` arguments of a multi-key command
+fn parse_numkeys(args: &mut Arguments) -> anyhow::Result<Vec<Bytes>> {
+    let numkeys: usize = args.pop_parse("numkeys")?;
+    if numkeys == 0 {
+        bail!("ERR numkeys should be greater than 0");
+    }
+    (0..numkeys).map(|_| args.pop("key")).collect()
+}
+
+/// Parse the `MIN` or `MAX` argument of ZMPOP/BZMPOP. Returns whether `MAX` was given.
+fn parse_min_max(arg: &[u8]) -> anyhow::Result<bool> {
+    match arg.to_ascii_uppercase().as_slice() {
+        b"MIN" => Ok(false),
+        b"MAX" => Ok(true),
+        _ => bail!("ERR syntax error"),
+    }
+}
+
+/// Parse the optional `LIMIT` option of SINTERCARD and ZINTERCARD (`0` for no limit)
+fn parse_card_limit(args: &mut Arguments) -> anyhow::Result<usize> {
+    match args.pop_optional() {
+        Some(arg) if arg.eq_ignore_ascii_case(b"LIMIT") => {
+            match args.pop_parse::<i64>("limit")?.try_into() {
+                Ok(limit) => Ok(limit),
+                Err(_) => bail!("ERR LIMIT can't be negative"),
+            }
+        }
+        Some(_) => bail!("ERR syntax error"),
+        None => Ok(0),
+    }
+}
+
+/// Parse the optional count of SRANDMEMBER, HRANDFIELD and ZRANDMEMBER, where a negative
+/// count allows repeated items. As in Redis, counts are limited to half the `i64` range.
+fn parse_random_count(args: &mut Arguments) -> anyhow::Result<Option<i64>> {
+    match args.pop_parse_optional::<i64>()? {
+        Some(count) if count.unsigned_abs() > (i64::MAX / 2) as u64 => {
+            bail!("ERR value is out of range")
+        }
+        count => Ok(count),
+    }
+}
+
+/// Parse the argument of a `COUNT` option, which must be positive
+fn parse_count(args: &mut Arguments) -> anyhow::Result<usize> {
+    match args.pop_parse("count")? {
+        0 => bail!("ERR count should be greater than 0"),
+        count => Ok(count),
+    }
+}
+
+/// Destination key of a geo search, and whether to store distances instead of geohashes
+type GeoStoreDest = (Bytes, bool);
+
+/// Parse the query of GEOSEARCH, GEOSEARCHSTORE, or the legacy GEORADIUS commands, where the
+/// legacy commands take the center and radius before the options. Returns the query, the extra
+/// reply information, and the destination key (from GEOSEARCHSTORE, or the `STORE` and
+/// `STOREDIST` options) along with whether to store distances.
+fn parse_geosearch_query(
+    args: &mut Arguments,
+    mut dest: Option<Bytes>,
+) -> anyhow::Result<(GeoSearchQuery, GeoSearchWith, Option<GeoStoreDest>)> {
+    let command = args.command().to_owned();
+    let is_search = command.starts_with("GEOSEARCH");
+    let is_store = command == "GEOSEARCHSTORE";
+    let is_read_only = command.ends_with("_RO");
+
+    let mut origin = None;
+    let mut shape = None;
+    let mut unit = GeoUnit::default();
+    if !is_search {
+        origin = Some(match command.starts_with("GEORADIUSBYMEMBER") {
+            true => GeoOrigin::Member(args.pop("member")?),
+            false => GeoOrigin::Coord(parse_geo_coord(args)?),
+        });
+        shape = Some(GeoShape::Radius(parse_geo_radius(args)?));
+        unit = parse_geo_unit(&args.pop("unit")?)?;
+    }
+    let mut order = None;
+    let mut count = None;
+    let mut any = false;
+    let mut with = GeoSearchWith::default();
+    let mut store_dist = false;
+    while let Some(arg) = args.pop_optional() {
+        match arg.to_ascii_uppercase().as_slice() {
+            b"FROMMEMBER" if is_search && origin.is_none() => {
+                origin = Some(GeoOrigin::Member(args.pop("member")?));
+            }
+            b"FROMLONLAT" if is_search && origin.is_none() => {
+                origin = Some(GeoOrigin::Coord(parse_geo_coord(args)?));
+            }
+            b"BYRADIUS" if is_search && shape.is_none() => {
+                shape = Some(GeoShape::Radius(parse_geo_radius(args)?));
+                unit = parse_geo_unit(&args.pop("unit")?)?;
+            }
+            b"BYBOX" if is_search && shape.is_none() => {
+                let width: f64 = args.pop_parse("width")?;
+                let height: f64 = args.pop_parse("height")?;
+                if width < 0.0 || height < 0.0 {
+                    bail!("ERR height or width cannot be negative");
+                }
+                unit = parse_geo_unit(&args.pop("unit")?)?;
+                shape = Some(GeoShape::Box(width, height));
+            }
+            b"BYPOLYGON" if is_search && shape.is_none() => {
+                let num_vertices: usize = args.pop_parse("number of vertices")?;
+                if num_vertices < 3 {
+                    bail!("ERR a polygon needs at least 3 vertices");
+                }
+                let vertices = (0..num_vertices)
+                    .map(|_| parse_geo_coord(args))
+                    .collect::<anyhow::Result<_>>()?;
+                unit = parse_geo_unit(&args.pop("unit")?)?;
+                shape = Some(GeoShape::Polygon(vertices));
+            }
+            b"FROMMEMBER" | b"FROMLONLAT" if is_search => bail!(GEOSEARCH_ORIGIN_ERROR),
+            b"BYRADIUS" | b"BYBOX" | b"BYPOLYGON" if is_search => bail!(GEOSEARCH_SHAPE_ERROR),
+            b"ASC" => order = Some(GeoOrder::Asc),
+            b"DESC" => order = Some(GeoOrder::Desc),
+            b"COUNT" => {
+                let num: i64 = args.pop_parse("count")?;
+                if num <= 0 {
+                    bail!("ERR COUNT must be > 0");
+                }
+                count = Some(num.try_into()?);
+                // ANY can only directly follow the count
+                let next = args.remaining().front().and_then(RespValue::as_bytes);
+                if next.is_some_and(|next| next.eq_ignore_ascii_case(b"ANY")) {
+                    args.pop("ANY")?;
+                    any = true;
+                }
+            }
+            b"WITHDIST" => with.dist = true,
+            b"WITHCOORD" => with.coord = true,
+            b"WITHHASH" => with.hash = true,
+            b"STOREDIST" if is_store => store_dist = true,
+            b"STORE" if !is_search && !is_read_only => {
+                dest = Some(args.pop("key")?);
+                store_dist = false;
+            }
+            b"STOREDIST" if !is_search && !is_read_only => {
+                dest = Some(args.pop("key")?);
+                store_dist = true;
+            }
+            _ => bail!("ERR syntax error"),
+        }
+    }
+    let Some(origin) = origin else {
+        bail!(GEOSEARCH_ORIGIN_ERROR);
+    };
+    let Some(shape) = shape else {
+        bail!(GEOSEARCH_SHAPE_ERROR);
+    };
+    if dest.is_some() && (with.dist || with.coord || with.hash) {
+        match is_store {
+            true => bail!("ERR GEOSEARCHSTORE is not compatible with WITHDIST, WITHHASH and WITHCOORD options"),
+            false => bail!("ERR STORE option in GEORADIUS is not compatible with WITHDIST, WITHHASH and WITHCOORD options"),
+        }
+    }
+
+    let query = GeoSearchQuery {
+        origin,
+        shape,
+        unit,
+        order,
+        count,
+        any,
+    };
+    Ok((query, with, dest.map(|dest| (dest, store_dist))))
+}
+
+/// Parse a longitude and latitude to search from
+fn parse_geo_coord(args: &mut Arguments) -> anyhow::Result<(f64, f64)> {
+    let lon = args.pop_parse("longitude")?;
+    let lat = args.pop_parse("latitude")?;
+    if !validate_lon(lon) || !validate_lat(lat) {
+        bail!("ERR invalid longitude,latitude pair {lon:.6},{lat:.6}");
+    }
+    Ok((lon, lat))
+}
+
+/// Parse the radius of a geo search
+fn parse_geo_radius(args: &mut Arguments) -> anyhow::Result<f64> {
+    let radius: f64 = args.pop_parse("radius")?;
+    if radius < 0.0 {
+        bail!("ERR radius cannot be negative");
+    }
+    Ok(radius)
+}
+
+const GEOSEARCH_ORIGIN_ERROR: &str =
+    "ERR exactly one of FROMMEMBER or FROMLONLAT can be specified for GEOSEARCH";
+const GEOSEARCH_SHAPE_ERROR: &str =
+    "ERR exactly one of BYRADIUS, BYBOX and BYPOLYGON arguments must be provided for GEOSEARCH command";
+
+/// Parse the unit of a geo distance (`m`, `km`, `ft`, or `mi`)
+fn parse_geo_unit(arg: &[u8]) -> anyhow::Result<GeoUnit> {
+    match arg.to_ascii_lowercase().as_slice() {
+        b"m" => Ok(GeoUnit::Meters),
+        b"km" => Ok(GeoUnit::Kilometers),
+        b"ft" => Ok(GeoUnit::Feet),
+        b"mi" => Ok(GeoUnit::Miles),
+        _ => bail!("ERR unsupported unit provided. please use M, KM, FT, MI"),
+    }
+}
+
+/// Parse the timeout argument of a blocking pop command (in seconds, `0` to block forever)
+/// into milliseconds
+fn parse_block_timeout(args: &mut Arguments) -> anyhow::Result<u64> {
+    let timeout = args.pop_parse::<f64>("timeout")?;
+    if !timeout.is_finite() || timeout < 0.0 {
+        bail!("ERR timeout is negative");
+    }
+    Ok((timeout * 1000.0).round() as u64)
+}
+
+/// Parse an expiration condition option (`NX`, `XX`, `GT`, or `LT`)
+fn parse_expiry_condition(arg: &[u8]) -> Option<ExpiryCondition> {
+    match arg.to_ascii_uppercase().as_slice() {
+        b"NX" => Some(ExpiryCondition::NotExists),
+        b"XX" => Some(ExpiryCondition::Exists),
+        b"GT" => Some(ExpiryCondition::GreaterThan),
+        b"LT" => Some(ExpiryCondition::LessThan),
+        _ => None,
+    }
+}
+
+/// Parse the `FIELDS numfields field [field ...]` arguments of the hash field expiration
+/// commands. Set `keyword_popped` if the `FIELDS` keyword was already popped.
+fn parse_fields(args: &mut Arguments, keyword_popped: bool) -> anyhow::Result<Vec<Bytes>> {
+    if !keyword_popped && !args.pop("FIELDS")?.eq_ignore_ascii_case(b"FIELDS") {
+        bail!("ERR Mandatory argument FIELDS is missing or not at the right position");
+    }
+    let num_fields: usize = args.pop_parse("numfields")?;
+    if num_fields == 0 {
+        bail!("ERR Parameter `numFields` should be greater than 0");
+    }
+    let mut fields = Vec::with_capacity(num_fields);
+    for _ in 0..num_fields {
+        fields.push(args.pop("field")?);
+    }
+    Ok(fields)
+}
+
+/// Parse the bit offset argument of SETBIT and GETBIT (must fit within the 512MB string limit)
+fn parse_bit_offset(args: &mut Arguments) -> anyhow::Result<usize> {
+    let offset: i64 = args.pop_parse("offset")?;
+    if !(0..1 << 32).contains(&offset) {
+        bail!("ERR bit offset is not an integer or out of range");
+    }
+    Ok(offset as usize)
+}
+
+/// Parse the `encoding offset` arguments of a BITFIELD operation (e.g. `i8 #2` or `u4 100`).
+/// Offsets prefixed with `#` are multiplied by the width of the field.
+fn parse_bit_field(args: &mut Arguments) -> anyhow::Result<BitField> {
+    let encoding = args.pop("encoding")?;
+    let bits = std::str::from_utf8(&encoding[1.min(encoding.len())..])
+        .ok()
+        .and_then(|bits| bits.parse().ok());
+    let (signed, bits) = match (encoding.first(), bits) {
+        (Some(b'i' | b'I'), Some(bits @ 1..=64)) => (true, bits),
+        (Some(b'u' | b'U'), Some(bits @ 1..=63)) => (false, bits),
+        _ => bail!(
+            "ERR Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is."
+        ),
+    };
+    let offset = args.pop("offset")?;
+    let (offset, multiplier) = match offset.strip_prefix(b"#") {
+        Some(offset) => (offset, i64::from(bits)),
+        None => (offset.as_ref(), 1),
+    };
+    let offset = std::str::from_utf8(offset)
+        .ok()
+        .and_then(|offset| offset.parse::<i64>().ok())
+        .and_then(|offset| offset.checked_mul(multiplier))
+        .filter(|offset| (0..=(1 << 32) - i64::from(bits)).contains(offset));
+    let Some(offset) = offset else {
+        bail!("ERR bit offset is not an integer or out of range");
+    };
+    Ok(BitField {
+        signed,
+        bits,
+        offset: offset as usize,
+    })
+}
+
+/// Parse the optional `start [end [BYTE | BIT]]` arguments of BITCOUNT and BITPOS.
+/// Set `end_required` if the end index must be given along with the start index.
+fn parse_bit_range(args: &mut Arguments, end_required: bool) -> anyhow::Result<Option<BitRange>> {
+    let Some(start) = args.pop_parse_optional()? else {
+        return Ok(None);
+    };
+    let end = args.pop_parse_optional()?;
+    if end.is_none() && end_required {
+        bail!("ERR syntax error");
+    }
+    let unit = match args.pop_optional() {
+        None => BitRangeUnit::Byte,
+        Some(arg) => match arg.to_ascii_uppercase().as_slice() {
+            b"BYTE" => BitRangeUnit::Byte,
+            b"BIT" => BitRangeUnit::Bit,
+            _ => bail!("ERR syntax error"),
+        },
+    };
+    Ok(Some(BitRange { start, end, unit }))
+}
+
+/// Parse the time argument of an uppercase `EX`, `PX`, `EXAT` or `PXAT` option. Returns `None`
+/// if the option isn't an expiration.
+fn parse_expiry_option(option: &[u8], args: &mut Arguments) -> anyhow::Result<Option<Expiry>> {
+    if !matches!(option, b"EX" | b"PX" | b"EXAT" | b"PXAT") {
+        return Ok(None);
+    }
+    let time: i64 = args.pop_parse("time")?;
+    let millis = match option {
+        b"EX" | b"EXAT" => time.checked_mul(1000),
+        _ => Some(time),
+    };
+    let Some(millis) = millis.filter(|millis| *millis > 0) else {
+        bail!(
+            "ERR invalid expire time in '{}' command",
+            args.command().to_lowercase()
+        );
+    };
+    Ok(Some(match option {
+        b"EX" | b"PX" => Expiry::In(millis),
+        _ => Expiry::At(millis),
+    }))
+}
@@ -30,7 +30,7 @@ pub async fn process_incoming(
     notifiers: Arc<Notifiers>,
 ) {
     let mut cxn = RespCodec::framed_io(BufWriter::new(BufReader::new(&mut tcp_stream)));
-    let mut authed = !config.auth.is_some();
+    let mut authed = config.auth.is_none();
 
     while let Some(value) = cxn.next().await {
         let response =
@@ -103,7 +103,7 @@ pub async fn process_incoming(
             };
 
         debug!("Response: {:?}", response);
-        let write_err = if cxn.read_buffer().len() > 0 {
+        let write_err = if !cxn.read_buffer().is_empty() {
             cxn.feed(response).await.err() // feed response if reader has more data (e.g. client is pipelining)
         } else {
             cxn.send(response).await.err()
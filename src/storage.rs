@@ -1,19 +1,23 @@
 use std::{
     collections::{BTreeMap, HashMap, HashSet, VecDeque},
-    time::Duration,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use bytes::Bytes;
-use tokio::time::Instant;
+use rand::seq::{IndexedRandom, IteratorRandom, SliceRandom};
 
 mod base;
 pub use base::*;
+pub mod bitmap;
 pub mod geo;
+pub mod hash;
+pub mod hyperloglog;
 pub mod list;
 pub mod rdb;
 pub mod set;
 pub mod sorted_set;
 pub mod stream;
+pub mod string;
 
 /// Common result type for some storage operations
 pub type StorageResult<T> = Result<T, Bytes>;
@@ -27,7 +31,8 @@ pub struct MemoryStorage {
 /// Redis object stored in memory
 #[derive(Debug)]
 pub struct RedisObject {
-    expiration: Option<Instant>,
+    /// Expiration in Unix time milliseconds
+    expiration: Option<u64>,
     data: RedisDataType,
 }
 
@@ -35,12 +40,121 @@ pub struct RedisObject {
 #[derive(Debug, PartialEq)]
 pub enum RedisDataType {
     String(Bytes),
+    /// A string holding an integer, stored natively
+    Integer(i64),
     List(VecDeque<Bytes>),
     Stream(BTreeMap<stream::StreamId, Vec<(Bytes, Bytes)>>),
     Set(HashSet<Bytes>),
+    Hash(hash::Hash),
     SortedSet(sorted_set::SortedSet),
 }
 
+/// An expiration given in a command
+#[derive(Debug, Clone, Copy)]
+pub enum Expiry {
+    /// Milliseconds from now
+    In(i64),
+    /// Unix time in milliseconds
+    At(i64),
+}
+
+impl Expiry {
+    /// Get the expiration in Unix time milliseconds (clamped to 0 if negative)
+    pub fn unix_millis(self) -> u64 {
+        let unix_millis = match self {
+            Self::In(ttl_millis) => (unix_time_millis() as i64).saturating_add(ttl_millis),
+            Self::At(unix_millis) => unix_millis,
+        };
+        unix_millis.max(0) as u64
+    }
+}
+
+/// Get the current Unix time in milliseconds
+pub fn unix_time_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+/// Randomly sample the items: up to `count` distinct items if positive, or `-count` items
+/// that may repeat if negative
+fn random_sample<T>(items: impl Iterator<Item = T>, count: i64) -> Vec<T>
+where
+    T: Clone,
+{
+    let mut rng = rand::rng();
+    match usize::try_from(count) {
+        Ok(count) => {
+            let mut sample = items.choose_multiple(&mut rng, count);
+            sample.shuffle(&mut rng);
+            sample
+        }
+        Err(_) => {
+            let items: Vec<_> = items.collect();
+            (0..count.unsigned_abs())
+                .map_while(|_| items.choose(&mut rng).cloned())
+                .collect()
+        }
+    }
+}
+
+/// Condition for updating an expiration (the `NX`, `XX`, `GT`, and `LT` options)
+#[derive(Debug, Clone, Copy)]
+pub enum ExpiryCondition {
+    /// Only set if there's no current expiration
+    NotExists,
+    /// Only set if there's a current expiration
+    Exists,
+    /// Only set if the new expiration is later than the current one
+    GreaterThan,
+    /// Only set if the new expiration is earlier than the current one
+    LessThan,
+}
+
+impl ExpiryCondition {
+    /// Check the condition against the current and new expiration. No current expiration
+    /// is treated as an infinite TTL.
+    pub fn is_met(self, current: Option<u64>, new: u64) -> bool {
+        match (self, current) {
+            (Self::NotExists, current) => current.is_none(),
+            (Self::Exists, current) => current.is_some(),
+            (Self::GreaterThan, current) => current.is_some_and(|current| new > current),
+            (Self::LessThan, current) => current.is_none_or(|current| new < current),
+        }
+    }
+}
+
+/// How to update the expiration of a key when writing it
+#[derive(Debug, Clone, Copy, Default)]
+pub enum ExpiryUpdate {
+    /// Remove the current expiration
+    #[default]
+    Clear,
+    /// Keep the current expiration
+    Keep,
+    /// Set a new expiration
+    Set(Expiry),
+}
+
+/// Condition for setting a key (the `NX` and `XX` options)
+#[derive(Debug, Clone, Copy)]
+pub enum SetCondition {
+    /// Only set if the key doesn't exist
+    NotExists,
+    /// Only set if the key already exists
+    Exists,
+}
+
+/// Options for setting a string value
+#[derive(Debug, Default)]
+pub struct SetOptions {
+    pub condition: Option<SetCondition>,
+    pub expiry: ExpiryUpdate,
+    /// Return the previous value
+    pub get: bool,
+}
+
 impl MemoryStorage {
     /// Get a reference for the object data at the given key. Will return `None` if missing or expired.
     fn get(&self, key: &Bytes) -> Option<&RedisDataType> {
@@ -67,7 +181,7 @@ impl MemoryStorage {
             .entry(key)
             .and_modify(|o| {
                 if !o.is_current() {
-                    std::mem::swap(o, &mut default_fn())
+                    *o = default_fn()
                 }
             })
             .or_insert_with(default_fn);
@@ -75,6 +189,17 @@ impl MemoryStorage {
     }
 }
 
+impl RedisDataType {
+    /// Get the value of a string (or integer) as bytes
+    fn to_string_bytes(&self) -> Option<Bytes> {
+        match self {
+            Self::String(bytes) => Some(bytes.clone()),
+            Self::Integer(int) => Some(Bytes::from(int.to_string())),
+            _ => None,
+        }
+    }
+}
+
 impl RedisObject {
     pub fn new(data: RedisDataType) -> Self {
         Self {
@@ -95,25 +220,31 @@ impl RedisObject {
         Self::new(RedisDataType::Stream(BTreeMap::new()))
     }
 
-    pub fn new_with_ttl(data: RedisDataType, ttl_millis: Option<u64>) -> Self {
-        Self {
-            expiration: ttl_millis.map(|ttl| (Instant::now() + Duration::from_millis(ttl))),
-            data,
-        }
+    pub fn new_with_expiration(data: RedisDataType, expiration: Option<u64>) -> Self {
+        Self { expiration, data }
     }
 
     fn is_current(&self) -> bool {
-        if let Some(expiration) = self.expiration {
-            Instant::now() <= expiration
-        } else {
-            true
+        if self
+            .expiration
+            .is_some_and(|expiration| unix_time_millis() > expiration)
+        {
+            return false;
+        }
+        match &self.data {
+            RedisDataType::Hash(hash) => hash.has_current_fields(),
+            _ => true,
         }
     }
 
     fn is_persist_supported(&self) -> bool {
         matches!(
             self.data,
-            RedisDataType::String(_) | RedisDataType::List(_) | RedisDataType::Set(_)
+            RedisDataType::String(_)
+                | RedisDataType::Integer(_)
+                | RedisDataType::List(_)
+                | RedisDataType::Set(_)
+                | RedisDataType::Hash(_)
         )
     }
 }
@@ -1,53 +1,169 @@
 use bytes::Bytes;
-use tokio::time::Instant;
 
-use super::{MemoryStorage, RedisDataType, RedisObject, StorageResult};
+use super::{
+    hash::{NAN_OR_INFINITY, OVERFLOW},
+    random_sample,
+    string::NOT_STRING,
+    unix_time_millis, ExpiryCondition, ExpiryUpdate, MemoryStorage, RedisDataType, RedisObject,
+    SetCondition, SetOptions, StorageResult,
+};
 
 /// Base storage interface
 pub trait Storage {
     fn get(&self, key: &Bytes) -> Option<Bytes>;
-    fn set(&mut self, key: Bytes, val: Bytes, ttl_millis: Option<u64>);
-    fn ttl(&self, key: &Bytes) -> i64;
+    /// Set the string value with the given options. Returns whether the value was set, and
+    /// the previous value if requested with the `get` option.
+    fn set(
+        &mut self,
+        key: Bytes,
+        val: Bytes,
+        options: SetOptions,
+    ) -> StorageResult<(bool, Option<Bytes>)>;
+    /// Get the string values of multiple keys. Missing keys and other types are `None`.
+    fn mget(&self, keys: &[Bytes]) -> Vec<Option<Bytes>>;
+    /// Set multiple string values, removing any expirations
+    fn mset(&mut self, pairs: Vec<(Bytes, Bytes)>);
+    /// Set multiple string values only if none of the keys exist. Returns whether the values were set.
+    fn msetnx(&mut self, pairs: Vec<(Bytes, Bytes)>) -> bool;
+    /// Get the string value and update its expiration
+    fn getex(&mut self, key: &Bytes, expiry: ExpiryUpdate) -> StorageResult<Option<Bytes>>;
+    /// Get the string value and delete the key
+    fn getdel(&mut self, key: &Bytes) -> StorageResult<Option<Bytes>>;
+    /// Get the remaining TTL in milliseconds. Returns `-1` if the key has no expiration,
+    /// or `-2` if the key doesn't exist.
+    fn pttl(&self, key: &Bytes) -> i64;
+    /// Get the expiration in Unix time milliseconds. Returns `-1` if the key has no
+    /// expiration, or `-2` if the key doesn't exist.
+    fn expire_time(&self, key: &Bytes) -> i64;
+    /// Set the expiration of the key in Unix time milliseconds, deleting the key if the
+    /// time is in the past. Returns `false` if the key doesn't exist or the condition wasn't met.
+    fn expire(&mut self, key: &Bytes, expires_at: u64, condition: Option<ExpiryCondition>) -> bool;
+    /// Remove the expiration of the key. Returns `false` if the key doesn't exist or has no expiration.
+    fn persist(&mut self, key: &Bytes) -> bool;
     fn kind(&self, key: &Bytes) -> Bytes;
     fn del(&mut self, key: &Bytes) -> bool;
-    fn incr(&mut self, key: Bytes) -> StorageResult<i64>;
+    /// Increment the integer value by the given amount, returning the new value
+    fn incrby(&mut self, key: Bytes, incr: i64) -> StorageResult<i64>;
+    /// Increment the float value by the given amount, returning the new value
+    fn incrbyfloat(&mut self, key: Bytes, incr: f64) -> StorageResult<f64>;
     fn size(&self) -> i64;
     fn keys(&self) -> Vec<Bytes>;
+    /// Get a random key that isn't expired.
+    ///
+    /// This samples the whole keyspace, since the map can't pick a random entry directly, so it
+    /// takes O(n) time in the number of keys while the storage lock is held.
+    fn random_key(&self) -> Option<Bytes>;
     fn flush(&mut self);
-    fn cleanup_expired(&mut self) -> usize;
+    /// Remove expired keys and hash fields. Returns the number of keys and fields removed.
+    fn cleanup_expired(&mut self) -> (usize, usize);
 }
 
 impl Storage for MemoryStorage {
     fn get(&self, key: &Bytes) -> Option<Bytes> {
-        match self.get(key) {
-            Some(RedisDataType::String(bytes)) => Some(bytes.clone()),
+        self.get(key).and_then(RedisDataType::to_string_bytes)
+    }
+
+    fn set(
+        &mut self,
+        key: Bytes,
+        val: Bytes,
+        options: SetOptions,
+    ) -> StorageResult<(bool, Option<Bytes>)> {
+        let current = self.data.get(&key).filter(|o| o.is_current());
+        let previous = match current {
+            Some(obj) if options.get => Some(obj.data.to_string_bytes().ok_or(NOT_STRING)?),
             _ => None,
+        };
+        let is_met = match options.condition {
+            Some(SetCondition::NotExists) => current.is_none(),
+            Some(SetCondition::Exists) => current.is_some(),
+            None => true,
+        };
+        if !is_met {
+            return Ok((false, previous));
         }
-    }
 
-    fn set(&mut self, key: Bytes, val: Bytes, ttl_millis: Option<u64>) {
-        let object = RedisObject::new_with_ttl(RedisDataType::String(val), ttl_millis);
+        let expiration = match options.expiry {
+            ExpiryUpdate::Clear => None,
+            ExpiryUpdate::Keep => current.and_then(|o| o.expiration),
+            ExpiryUpdate::Set(expiry) => Some(expiry.unix_millis()),
+        };
+        let object = RedisObject::new_with_expiration(RedisDataType::String(val), expiration);
         self.data.insert(key, object);
+
+        Ok((true, previous))
+    }
+
+    fn mget(&self, keys: &[Bytes]) -> Vec<Option<Bytes>> {
+        keys.iter().map(|key| Storage::get(self, key)).collect()
+    }
+
+    fn mset(&mut self, pairs: Vec<(Bytes, Bytes)>) {
+        for (key, val) in pairs {
+            self.data
+                .insert(key, RedisObject::new(RedisDataType::String(val)));
+        }
+    }
+
+    fn msetnx(&mut self, pairs: Vec<(Bytes, Bytes)>) -> bool {
+        if pairs.iter().any(|(key, _)| self.get(key).is_some()) {
+            return false;
+        }
+        self.mset(pairs);
+        true
+    }
+
+    fn getex(&mut self, key: &Bytes, expiry: ExpiryUpdate) -> StorageResult<Option<Bytes>> {
+        let Some(obj) = self.data.get_mut(key).filter(|o| o.is_current()) else {
+            return Ok(None);
+        };
+        let val = obj.data.to_string_bytes().ok_or(NOT_STRING)?;
+        match expiry {
+            ExpiryUpdate::Clear => obj.expiration = None,
+            ExpiryUpdate::Keep => {}
+            ExpiryUpdate::Set(expiry) => {
+                let expires_at = expiry.unix_millis();
+                if expires_at <= unix_time_millis() {
+                    self.data.remove(key);
+                } else {
+                    obj.expiration = Some(expires_at);
+                }
+            }
+        }
+
+        Ok(Some(val))
+    }
+
+    fn getdel(&mut self, key: &Bytes) -> StorageResult<Option<Bytes>> {
+        let Some(data) = self.get(key) else {
+            return Ok(None);
+        };
+        let val = data.to_string_bytes().ok_or(NOT_STRING)?;
+        self.data.remove(key);
+        Ok(Some(val))
     }
 
     fn kind(&self, key: &Bytes) -> Bytes {
         match self.get(key) {
             Some(data_type) => match data_type {
-                RedisDataType::String(_) => Bytes::from_static(b"string"),
+                RedisDataType::String(_) | RedisDataType::Integer(_) => {
+                    Bytes::from_static(b"string")
+                }
                 RedisDataType::List(_) => Bytes::from_static(b"list"),
                 RedisDataType::Stream(_) => Bytes::from_static(b"stream"),
                 RedisDataType::Set(_) => Bytes::from_static(b"set"),
                 RedisDataType::SortedSet(_) => Bytes::from_static(b"zset"),
+                RedisDataType::Hash(_) => Bytes::from_static(b"hash"),
             },
             None => Bytes::from_static(b"none"),
         }
     }
 
-    fn ttl(&self, key: &Bytes) -> i64 {
+    fn pttl(&self, key: &Bytes) -> i64 {
         match self.data.get(key).filter(|o| o.is_current()) {
             Some(obj) => match obj.expiration {
-                Some(expiration) => (expiration - Instant::now())
-                    .as_secs()
+                Some(expiration) => expiration
+                    .saturating_sub(unix_time_millis())
                     .try_into()
                     .unwrap_or_default(),
                 None => -1,
@@ -56,32 +172,78 @@ impl Storage for MemoryStorage {
         }
     }
 
+    fn expire_time(&self, key: &Bytes) -> i64 {
+        match self.data.get(key).filter(|o| o.is_current()) {
+            Some(obj) => match obj.expiration {
+                Some(expiration) => expiration.try_into().unwrap_or(i64::MAX),
+                None => -1,
+            },
+            None => -2,
+        }
+    }
+
+    fn expire(&mut self, key: &Bytes, expires_at: u64, condition: Option<ExpiryCondition>) -> bool {
+        let Some(obj) = self.data.get_mut(key).filter(|o| o.is_current()) else {
+            return false;
+        };
+        if condition.is_some_and(|c| !c.is_met(obj.expiration, expires_at)) {
+            return false;
+        }
+        if expires_at <= unix_time_millis() {
+            self.data.remove(key);
+        } else {
+            obj.expiration = Some(expires_at);
+        }
+        true
+    }
+
+    fn persist(&mut self, key: &Bytes) -> bool {
+        self.data
+            .get_mut(key)
+            .filter(|o| o.is_current())
+            .is_some_and(|obj| obj.expiration.take().is_some())
+    }
+
     fn del(&mut self, key: &Bytes) -> bool {
         self.data.remove(key).is_some()
     }
 
-    fn incr(&mut self, key: Bytes) -> StorageResult<i64> {
-        const INCR_ERROR: Bytes =
-            Bytes::from_static(b"ERR value is not an integer or out of range");
-
-        let val = self.get_entry_with_default(key.clone(), || {
-            RedisObject::new(RedisDataType::String(Bytes::from_static(b"0")))
-        });
-        let bytes = match val.data {
-            RedisDataType::String(ref bytes) => bytes,
-            _ => Err(INCR_ERROR)?,
+    fn incrby(&mut self, key: Bytes, incr: i64) -> StorageResult<i64> {
+        let val = self.get_entry_with_default(key, || RedisObject::new(RedisDataType::Integer(0)));
+        let current = match val.data {
+            RedisDataType::Integer(int) => int,
+            RedisDataType::String(ref bytes) => std::str::from_utf8(bytes)
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .ok_or(NOT_INTEGER)?,
+            _ => return Err(NOT_STRING),
         };
-        let mut int: i64 = std::str::from_utf8(bytes)
-            .map_err(|_| INCR_ERROR)?
-            .parse()
-            .map_err(|_| INCR_ERROR)?;
-
-        int += 1;
-        self.set(key, Bytes::from(int.to_string()), None);
+        let int = current.checked_add(incr).ok_or(OVERFLOW)?;
+        val.data = RedisDataType::Integer(int);
 
         Ok(int)
     }
 
+    fn incrbyfloat(&mut self, key: Bytes, incr: f64) -> StorageResult<f64> {
+        let val = self.get_entry_with_default(key, || RedisObject::new(RedisDataType::Integer(0)));
+        let current = match val.data {
+            RedisDataType::Integer(int) => int as f64,
+            RedisDataType::String(ref bytes) => std::str::from_utf8(bytes)
+                .ok()
+                .and_then(|s| s.parse::<f64>().ok())
+                .filter(|float| float.is_finite())
+                .ok_or(NOT_FLOAT)?,
+            _ => return Err(NOT_STRING),
+        };
+        let float = current + incr;
+        if !float.is_finite() {
+            return Err(NAN_OR_INFINITY);
+        }
+        val.data = RedisDataType::String(Bytes::from(float.to_string()));
+
+        Ok(float)
+    }
+
     fn size(&self) -> i64 {
         let count = self.data.values().filter(|o| o.is_current()).count();
         count.try_into().unwrap_or_default()
@@ -91,11 +253,23 @@ impl Storage for MemoryStorage {
         self.data.keys().cloned().collect()
     }
 
+    fn random_key(&self) -> Option<Bytes> {
+        let keys = self.data.iter().filter(|(_, o)| o.is_current());
+        random_sample(keys.map(|(key, _)| key), 1).pop().cloned()
+    }
+
     fn flush(&mut self) {
         self.data.clear();
     }
 
-    fn cleanup_expired(&mut self) -> usize {
+    fn cleanup_expired(&mut self) -> (usize, usize) {
+        let mut expired_fields = 0;
+        for obj in self.data.values_mut() {
+            if let RedisDataType::Hash(hash) = &mut obj.data {
+                expired_fields += hash.remove_expired();
+            }
+        }
+
         let expired_keys: Vec<_> = self
             .data
             .iter()
@@ -106,6 +280,9 @@ impl Storage for MemoryStorage {
             self.data.remove(key);
         }
 
-        expired_keys.len()
+        (expired_keys.len(), expired_fields)
     }
 }
+
+const NOT_INTEGER: Bytes = Bytes::from_static(b"ERR value is not an integer or out of range");
+const NOT_FLOAT: Bytes = Bytes::from_static(b"ERR value is not a valid float");
@@ -0,0 +1,218 @@
+use bytes::{Bytes, BytesMut};
+
+use super::{
+    string::normalize_range, MemoryStorage, RedisDataType, RedisObject, StorageResult as Result,
+};
+
+mod bitmap_utils;
+
+/// Bitmap interface (operating on string values)
+pub trait BitmapStorage {
+    /// Set or clear the bit at the offset, growing the string if needed. Returns the previous bit.
+    fn setbit(&mut self, key: Bytes, offset: usize, bit: bool) -> Result<i64>;
+    fn getbit(&self, key: &Bytes, offset: usize) -> Result<i64>;
+    /// Count the set bits in the string, optionally within the given range
+    fn bitcount(&self, key: &Bytes, range: Option<BitRange>) -> Result<i64>;
+    /// Find the position of the first bit with the given value, optionally within the given
+    /// range. Returns `-1` if not found.
+    fn bitpos(&self, key: &Bytes, bit: bool, range: Option<BitRange>) -> Result<i64>;
+    /// Perform the bitwise operation between the strings and store the result in the destination
+    /// key (deleting it if the result is empty). Returns the length of the result.
+    fn bitop(&mut self, op: BitOp, dest: Bytes, keys: &[Bytes]) -> Result<i64>;
+    /// Perform the BITFIELD operations in order, growing the string if there are any writes.
+    /// Returns the result of each operation, or `None` if a write failed due to the `FAIL`
+    /// overflow behavior.
+    fn bitfield(&mut self, key: Bytes, ops: Vec<BitFieldOp>) -> Result<Vec<Option<i64>>>;
+}
+
+/// A range of a bitmap given as inclusive start and end indexes. Negative indexes count from
+/// the end of the string.
+#[derive(Debug, Clone, Copy)]
+pub struct BitRange {
+    pub start: i64,
+    /// The end index (defaults to the end of the string)
+    pub end: Option<i64>,
+    pub unit: BitRangeUnit,
+}
+
+/// The unit of the indexes of a [`BitRange`] (the `BYTE` and `BIT` options)
+#[derive(Debug, Clone, Copy, Default)]
+pub enum BitRangeUnit {
+    #[default]
+    Byte,
+    Bit,
+}
+
+/// Bitwise operation for BITOP
+#[derive(Debug, Clone, Copy)]
+pub enum BitOp {
+    And,
+    Or,
+    Xor,
+    Not,
+    /// Bits set in the first string but not in any of the others
+    Diff,
+}
+
+/// An integer field of a bitmap
+#[derive(Debug, Clone, Copy)]
+pub struct BitField {
+    pub signed: bool,
+    /// Width of the field (up to 64 bits if signed, and 63 bits if unsigned)
+    pub bits: u32,
+    /// Bit offset of the field
+    pub offset: usize,
+}
+
+/// How to handle overflows when writing a [`BitField`] (the `OVERFLOW` option)
+#[derive(Debug, Clone, Copy, Default)]
+pub enum BitFieldOverflow {
+    /// Wrap around, like two's complement arithmetic
+    #[default]
+    Wrap,
+    /// Saturate to the minimum or maximum value
+    Sat,
+    /// Don't write the value
+    Fail,
+}
+
+/// A BITFIELD operation
+#[derive(Debug)]
+pub enum BitFieldOp {
+    Get(BitField),
+    /// Set the value and return the previous value
+    Set(BitField, i64, BitFieldOverflow),
+    /// Increment the value and return the new value
+    IncrBy(BitField, i64, BitFieldOverflow),
+}
+
+impl BitmapStorage for MemoryStorage {
+    fn setbit(&mut self, key: Bytes, offset: usize, bit: bool) -> Result<i64> {
+        let string = self.get_string_entry(key)?;
+        let byte_idx = offset / 8;
+        let mut buf = BytesMut::from(std::mem::take(string));
+        if buf.len() <= byte_idx {
+            buf.resize(byte_idx + 1, 0);
+        }
+        let previous = bitmap_utils::get_bit(&buf, offset);
+        bitmap_utils::set_bit(&mut buf, offset, bit);
+        *string = buf.freeze();
+
+        Ok(previous.into())
+    }
+
+    fn getbit(&self, key: &Bytes, offset: usize) -> Result<i64> {
+        Ok(match self.get_string(key)? {
+            Some(string) => bitmap_utils::get_bit(&string, offset).into(),
+            None => 0,
+        })
+    }
+
+    fn bitcount(&self, key: &Bytes, range: Option<BitRange>) -> Result<i64> {
+        let Some(string) = self.get_string(key)? else {
+            return Ok(0);
+        };
+        let count = match bit_range(range, string.len()) {
+            Some((start, end)) => bitmap_utils::count_bits(&string, start, end),
+            None => 0,
+        };
+        Ok(count.try_into().unwrap_or_default())
+    }
+
+    fn bitpos(&self, key: &Bytes, bit: bool, range: Option<BitRange>) -> Result<i64> {
+        let string = self.get_string(key)?.unwrap_or_default();
+        if string.is_empty() {
+            return Ok(if bit { -1 } else { 0 });
+        }
+        let Some((start, end)) = bit_range(range, string.len()) else {
+            return Ok(-1);
+        };
+        let pos = match bitmap_utils::find_bit(&string, bit, start, end) {
+            Some(pos) => pos,
+            // when looking for a 0 without an end index, the string is treated as padded with zeros
+            None if !bit && range.is_none_or(|range| range.end.is_none()) => string.len() * 8,
+            None => return Ok(-1),
+        };
+        Ok(pos.try_into().unwrap_or_default())
+    }
+
+    fn bitop(&mut self, op: BitOp, dest: Bytes, keys: &[Bytes]) -> Result<i64> {
+        let strings = keys
+            .iter()
+            .map(|key| Ok(self.get_string(key)?.unwrap_or_default()))
+            .collect::<Result<Vec<_>>>()?;
+        let result = bitmap_utils::bitop(op, &strings);
+        let len = result.len();
+        if result.is_empty() {
+            self.data.remove(&dest);
+        } else {
+            let object = RedisObject::new(RedisDataType::String(Bytes::from(result)));
+            self.data.insert(dest, object);
+        }
+
+        Ok(len.try_into().unwrap_or_default())
+    }
+
+    fn bitfield(&mut self, key: Bytes, ops: Vec<BitFieldOp>) -> Result<Vec<Option<i64>>> {
+        if ops.iter().all(|op| matches!(op, BitFieldOp::Get(_))) {
+            let string = self.get_string(&key)?.unwrap_or_default();
+            return Ok(ops
+                .into_iter()
+                .map(|op| match op {
+                    BitFieldOp::Get(field) => Some(bitmap_utils::get_field(&string, field)),
+                    _ => unreachable!(),
+                })
+                .collect());
+        }
+
+        let end = ops
+            .iter()
+            .filter_map(|op| match op {
+                BitFieldOp::Get(_) => None,
+                BitFieldOp::Set(field, ..) | BitFieldOp::IncrBy(field, ..) => {
+                    Some((field.offset + field.bits as usize).div_ceil(8))
+                }
+            })
+            .max()
+            .unwrap_or_default();
+        let string = self.get_string_entry(key)?;
+        let mut buf = BytesMut::from(std::mem::take(string));
+        if buf.len() < end {
+            buf.resize(end, 0);
+        }
+        let results = ops
+            .into_iter()
+            .map(|op| match op {
+                BitFieldOp::Get(field) => Some(bitmap_utils::get_field(&buf, field)),
+                BitFieldOp::Set(field, value, overflow) => {
+                    let previous = bitmap_utils::get_field(&buf, field);
+                    let value = bitmap_utils::add_field(field, 0, value, overflow)?;
+                    bitmap_utils::set_field(&mut buf, field, value);
+                    Some(previous)
+                }
+                BitFieldOp::IncrBy(field, incr, overflow) => {
+                    let current = bitmap_utils::get_field(&buf, field);
+                    let value = bitmap_utils::add_field(field, current, incr, overflow)?;
+                    bitmap_utils::set_field(&mut buf, field, value);
+                    Some(value)
+                }
+            })
+            .collect();
+        *string = buf.freeze();
+
+        Ok(results)
+    }
+}
+
+/// Convert the range into inclusive start and end bit offsets for a string of the given
+/// length. Returns `None` if the range is empty.
+fn bit_range(range: Option<BitRange>, len: usize) -> Option<(usize, usize)> {
+    let Some(BitRange { start, end, unit }) = range else {
+        return normalize_range(0, -1, len * 8);
+    };
+    match unit {
+        BitRangeUnit::Byte => normalize_range(start, end.unwrap_or(-1), len)
+            .map(|(start, end)| (start * 8, end * 8 + 7)),
+        BitRangeUnit::Bit => normalize_range(start, end.unwrap_or(-1), len * 8),
+    }
+}
@@ -0,0 +1,222 @@
+//! Bit manipulation utilities. Bits are numbered from the most significant bit of the
+//! first byte, the same as Redis.
+
+use bytes::Bytes;
+
+use super::{BitField, BitFieldOverflow, BitOp};
+
+/// Get the bit at the offset. Bits past the end are 0.
+pub fn get_bit(bytes: &[u8], offset: usize) -> bool {
+    bytes
+        .get(offset / 8)
+        .is_some_and(|byte| byte & (0x80 >> (offset % 8)) != 0)
+}
+
+/// Set or clear the bit at the offset
+pub fn set_bit(bytes: &mut [u8], offset: usize, bit: bool) {
+    let mask = 0x80 >> (offset % 8);
+    if bit {
+        bytes[offset / 8] |= mask;
+    } else {
+        bytes[offset / 8] &= !mask;
+    }
+}
+
+/// Count the set bits between the inclusive start and end bit offsets
+pub fn count_bits(bytes: &[u8], start: usize, end: usize) -> usize {
+    let (first, last) = (start / 8, end / 8);
+    let head_mask = 0xFF >> (start % 8);
+    let tail_mask = 0xFF << (7 - end % 8);
+    if first == last {
+        return (bytes[first] & head_mask & tail_mask).count_ones() as usize;
+    }
+    let middle: u32 = bytes[first + 1..last].iter().map(|b| b.count_ones()).sum();
+    let ends = (bytes[first] & head_mask).count_ones() + (bytes[last] & tail_mask).count_ones();
+    (middle + ends) as usize
+}
+
+/// Find the offset of the first bit with the given value between the inclusive start
+/// and end bit offsets
+pub fn find_bit(bytes: &[u8], bit: bool, start: usize, end: usize) -> Option<usize> {
+    let (first, last) = (start / 8, end / 8);
+    for (idx, byte) in bytes.iter().enumerate().take(last + 1).skip(first) {
+        // flip the byte when looking for a 0, so we can always search for the first 1
+        let mut byte = if bit { *byte } else { !byte };
+        if idx == first {
+            byte &= 0xFF >> (start % 8);
+        }
+        if idx == last {
+            byte &= 0xFF << (7 - end % 8);
+        }
+        if byte != 0 {
+            return Some(idx * 8 + byte.leading_zeros() as usize);
+        }
+    }
+    None
+}
+
+/// Perform the bitwise operation on the strings. Shorter strings are padded with zero bytes
+/// to the length of the longest string.
+pub fn bitop(op: BitOp, strings: &[Bytes]) -> Vec<u8> {
+    let len = strings.iter().map(Bytes::len).max().unwrap_or_default();
+    let mut result = Vec::with_capacity(len);
+    for idx in 0..len {
+        let mut bytes = strings
+            .iter()
+            .map(|string| string.get(idx).copied().unwrap_or_default());
+        let first = bytes.next().unwrap_or_default();
+        result.push(match op {
+            BitOp::And => bytes.fold(first, |acc, byte| acc & byte),
+            BitOp::Or => bytes.fold(first, |acc, byte| acc | byte),
+            BitOp::Xor => bytes.fold(first, |acc, byte| acc ^ byte),
+            BitOp::Not => !first,
+            BitOp::Diff => first & !bytes.fold(0, |acc, byte| acc | byte),
+        });
+    }
+    result
+}
+
+/// Read the integer value of the field. Bits past the end are 0.
+pub fn get_field(bytes: &[u8], field: BitField) -> i64 {
+    let mut value = 0u64;
+    for idx in 0..field.bits as usize {
+        value = (value << 1) | u64::from(get_bit(bytes, field.offset + idx));
+    }
+    // extend the sign bit for negative values
+    if field.signed && field.bits < 64 && value >> (field.bits - 1) == 1 {
+        value |= u64::MAX << field.bits;
+    }
+    value as i64
+}
+
+/// Write the integer value to the field (the value should already be within the field's range)
+pub fn set_field(bytes: &mut [u8], field: BitField, value: i64) {
+    for idx in 0..field.bits {
+        let bit = (value as u64 >> (field.bits - 1 - idx)) & 1 == 1;
+        set_bit(bytes, field.offset + idx as usize, bit);
+    }
+}
+
+/// Add the increment to the current value of the field, handling overflow with the given
+/// behavior. Returns `None` if the value overflowed with the `FAIL` behavior.
+pub fn add_field(
+    field: BitField,
+    current: i64,
+    incr: i64,
+    overflow: BitFieldOverflow,
+) -> Option<i64> {
+    let (min, max) = match field.signed {
+        true => (
+            -(1i128 << (field.bits - 1)),
+            (1i128 << (field.bits - 1)) - 1,
+        ),
+        false => (0, (1i128 << field.bits) - 1),
+    };
+    let value = i128::from(current) + i128::from(incr);
+    if (min..=max).contains(&value) {
+        return Some(value as i64);
+    }
+    let value = match overflow {
+        BitFieldOverflow::Wrap => {
+            let wrapped = value & ((1i128 << field.bits) - 1);
+            if wrapped > max {
+                wrapped - (1i128 << field.bits)
+            } else {
+                wrapped
+            }
+        }
+        BitFieldOverflow::Sat => value.clamp(min, max),
+        BitFieldOverflow::Fail => return None,
+    };
+    Some(value as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_and_find() {
+        let bytes = [0b0000_0000, 0b1111_0000, 0b1111_1111];
+        assert!(get_bit(&bytes, 8));
+        assert!(!get_bit(&bytes, 12));
+        assert!(!get_bit(&bytes, 100));
+
+        assert_eq!(count_bits(&bytes, 0, 23), 12);
+        assert_eq!(count_bits(&bytes, 10, 17), 4);
+        assert_eq!(count_bits(&bytes, 12, 12), 0);
+
+        assert_eq!(find_bit(&bytes, true, 0, 23), Some(8));
+        assert_eq!(find_bit(&bytes, true, 10, 23), Some(10));
+        assert_eq!(find_bit(&bytes, false, 8, 23), Some(12));
+        assert_eq!(find_bit(&bytes, false, 16, 23), None);
+        assert_eq!(find_bit(&bytes, true, 0, 7), None);
+    }
+
+    #[test]
+    fn fields() {
+        let field = |signed, bits, offset| BitField {
+            signed,
+            bits,
+            offset,
+        };
+        let mut bytes = [0u8; 9];
+        set_field(&mut bytes, field(false, 8, 4), 255);
+        assert_eq!(bytes[..2], [0b0000_1111, 0b1111_0000]);
+        assert_eq!(get_field(&bytes, field(false, 8, 4)), 255);
+        assert_eq!(get_field(&bytes, field(true, 8, 4)), -1);
+        assert_eq!(get_field(&bytes, field(true, 4, 8)), -1);
+        assert_eq!(get_field(&bytes, field(false, 4, 100)), 0);
+
+        set_field(&mut bytes, field(true, 64, 8), i64::MIN);
+        assert_eq!(get_field(&bytes, field(true, 64, 8)), i64::MIN);
+        assert_eq!(get_field(&bytes, field(false, 63, 8)), 1 << 62);
+    }
+
+    #[test]
+    fn field_overflow() {
+        let u8_field = BitField {
+            signed: false,
+            bits: 8,
+            offset: 0,
+        };
+        let i8_field = BitField {
+            signed: true,
+            ..u8_field
+        };
+        let (wrap, sat, fail) = (
+            BitFieldOverflow::Wrap,
+            BitFieldOverflow::Sat,
+            BitFieldOverflow::Fail,
+        );
+        assert_eq!(add_field(u8_field, 250, 10, wrap), Some(4));
+        assert_eq!(add_field(u8_field, 250, 10, sat), Some(255));
+        assert_eq!(add_field(u8_field, 250, 10, fail), None);
+        assert_eq!(add_field(u8_field, 5, -10, wrap), Some(251));
+        assert_eq!(add_field(u8_field, 5, -10, sat), Some(0));
+        assert_eq!(add_field(i8_field, 120, 10, wrap), Some(-126));
+        assert_eq!(add_field(i8_field, -120, -10, sat), Some(-128));
+        assert_eq!(add_field(i8_field, 0, 127, fail), Some(127));
+
+        let i64_field = BitField {
+            bits: 64,
+            ..i8_field
+        };
+        assert_eq!(add_field(i64_field, i64::MAX, 1, wrap), Some(i64::MIN));
+        assert_eq!(add_field(i64_field, i64::MAX, 1, sat), Some(i64::MAX));
+    }
+
+    #[test]
+    fn bitwise_operations() {
+        let strings = [
+            Bytes::from_static(&[0b1100_1100, 0b1111_0000]),
+            Bytes::from_static(&[0b1010_1010]),
+        ];
+        assert_eq!(bitop(BitOp::And, &strings), [0b1000_1000, 0]);
+        assert_eq!(bitop(BitOp::Or, &strings), [0b1110_1110, 0b1111_0000]);
+        assert_eq!(bitop(BitOp::Xor, &strings), [0b0110_0110, 0b1111_0000]);
+        assert_eq!(bitop(BitOp::Diff, &strings), [0b0100_0100, 0b1111_0000]);
+        assert_eq!(bitop(BitOp::Not, &strings[1..]), [0b0101_0101]);
+        assert!(bitop(BitOp::Not, &[Bytes::new()]).is_empty());
+    }
+}
@@ -1,24 +1,128 @@
 use bytes::Bytes;
 
-use crate::storage::{
-    geo::geo_utils::{coord_to_score, haversine_dist_meters, score_to_coord},
-    sorted_set::RankedItem,
+use crate::storage::geo::geo_utils::{
+    coord_to_score, haversine_dist_meters, score_to_coord, score_to_geohash, shape_distance_meters,
+    shape_score_ranges,
 };
 
 use super::{
-    sorted_set::{SortedSet, SortedSetStorage},
-    MemoryStorage, StorageResult as Result,
+    sorted_set::{SortedSet, SortedSetStorage, ZAddOptions},
+    MemoryStorage, RedisDataType, RedisObject, StorageResult as Result,
 };
 
 mod geo_utils;
 pub use geo_utils::{validate_lat, validate_lon};
 
+/// Unit of distance for geo commands
+#[derive(Debug, Default, Clone, Copy)]
+pub enum GeoUnit {
+    #[default]
+    Meters,
+    Kilometers,
+    Feet,
+    Miles,
+}
+
+impl GeoUnit {
+    /// The number of meters in one unit
+    fn meters(self) -> f64 {
+        match self {
+            GeoUnit::Meters => 1.0,
+            GeoUnit::Kilometers => 1000.0,
+            GeoUnit::Feet => 0.3048,
+            GeoUnit::Miles => 1609.34,
+        }
+    }
+}
+
+/// The center of a GEOSEARCH area
+#[derive(Debug)]
+pub enum GeoOrigin {
+    /// The position of a member of the sorted set (`FROMMEMBER`)
+    Member(Bytes),
+    /// A longitude and latitude (`FROMLONLAT`)
+    Coord((f64, f64)),
+}
+
+/// The shape of a GEOSEARCH area
+#[derive(Debug, Clone)]
+pub enum GeoShape {
+    /// Circle with the radius (`BYRADIUS`)
+    Radius(f64),
+    /// Rectangle with the width and height, aligned to the meridians (`BYBOX`)
+    Box(f64, f64),
+    /// Polygon with the longitude and latitude of each vertex (`BYPOLYGON`), which doesn't
+    /// depend on the center. Edges are straight lines between the vertices on a longitude and
+    /// latitude map, and may cross the antimeridian.
+    Polygon(Vec<(f64, f64)>),
+}
+
+/// Ordering of GEOSEARCH matches by their distance from the center
+#[derive(Debug, Clone, Copy)]
+pub enum GeoOrder {
+    Asc,
+    Desc,
+}
+
+/// A GEOSEARCH query
+#[derive(Debug)]
+pub struct GeoSearchQuery {
+    pub origin: GeoOrigin,
+    pub shape: GeoShape,
+    /// Unit of the shape's dimensions and of the returned distances
+    pub unit: GeoUnit,
+    pub order: Option<GeoOrder>,
+    /// Maximum number of matches (the `COUNT` option)
+    pub count: Option<usize>,
+    /// Return the first matches found instead of the closest ones (the `ANY` option)
+    pub any: bool,
+}
+
+/// Extra information to reply with for each GEOSEARCH match
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GeoSearchWith {
+    pub dist: bool,
+    pub coord: bool,
+    pub hash: bool,
+}
+
+/// A member found by GEOSEARCH
+#[derive(Debug, PartialEq)]
+pub struct GeoMatch {
+    pub member: Bytes,
+    /// Distance from the center, in the query's unit
+    pub dist: f64,
+    /// The geohash of the member (its score)
+    pub hash: u64,
+    pub coord: (f64, f64),
+}
+
 /// Geo interface
 pub trait GeoStorage {
     fn geoadd(&mut self, key: Bytes, members: Vec<((f64, f64), Bytes)>) -> Result<i64>;
     fn geopos(&self, key: &Bytes, members: Vec<Bytes>) -> Result<Vec<Option<(f64, f64)>>>;
-    fn geodist(&self, key: &Bytes, member1: &Bytes, member2: &Bytes) -> Result<Option<f64>>;
-    fn geosearch(&self, key: &Bytes, from: (f64, f64), radius: f64) -> Result<Vec<Bytes>>;
+    /// Get the standard geohash strings of the members
+    fn geohash(&self, key: &Bytes, members: &[Bytes]) -> Result<Vec<Option<String>>>;
+    /// Get the distance between the members in the unit
+    fn geodist(
+        &self,
+        key: &Bytes,
+        member1: &Bytes,
+        member2: &Bytes,
+        unit: GeoUnit,
+    ) -> Result<Option<f64>>;
+    /// Find the members within the area of the query
+    fn geosearch(&self, key: &Bytes, query: &GeoSearchQuery) -> Result<Vec<GeoMatch>>;
+    /// Store the members within the area of the query at the destination key (deleting it if
+    /// there are none), scored by their geohash or by their distance if `store_dist` is set.
+    /// Returns the number of members stored.
+    fn geosearchstore(
+        &mut self,
+        dest: Bytes,
+        src: &Bytes,
+        query: &GeoSearchQuery,
+        store_dist: bool,
+    ) -> Result<i64>;
 }
 
 impl GeoStorage for MemoryStorage {
@@ -28,7 +132,8 @@ impl GeoStorage for MemoryStorage {
             .map(|(coord, member)| (coord_to_score(coord) as f64, member))
             .collect();
 
-        self.zadd(key, members)
+        let (num_added, _) = self.zadd(key, members, ZAddOptions::default())?;
+        Ok(num_added)
     }
 
     fn geopos(&self, key: &Bytes, members: Vec<Bytes>) -> Result<Vec<Option<(f64, f64)>>> {
@@ -43,7 +148,26 @@ impl GeoStorage for MemoryStorage {
         Ok(member_coords)
     }
 
-    fn geodist(&self, key: &Bytes, member1: &Bytes, member2: &Bytes) -> Result<Option<f64>> {
+    fn geohash(&self, key: &Bytes, members: &[Bytes]) -> Result<Vec<Option<String>>> {
+        let Some(SortedSet(hash, _)) = self.get_sorted_set(key)? else {
+            return Ok(vec![None; members.len()]);
+        };
+        Ok(members
+            .iter()
+            .map(|member| {
+                hash.get(member)
+                    .map(|score| score_to_geohash(*score as u64))
+            })
+            .collect())
+    }
+
+    fn geodist(
+        &self,
+        key: &Bytes,
+        member1: &Bytes,
+        member2: &Bytes,
+        unit: GeoUnit,
+    ) -> Result<Option<f64>> {
         let Some(SortedSet(hash, _)) = self.get_sorted_set(key)? else {
             return Ok(None);
         };
@@ -51,25 +175,223 @@ impl GeoStorage for MemoryStorage {
             (Some(score1), Some(score2)) => {
                 let origin = score_to_coord(*score1 as u64);
                 let dest = score_to_coord(*score2 as u64);
-                Some(haversine_dist_meters(origin, dest))
+                Some(haversine_dist_meters(origin, dest) / unit.meters())
             }
             _ => None,
         })
     }
 
-    fn geosearch(&self, key: &Bytes, from_coords: (f64, f64), radius: f64) -> Result<Vec<Bytes>> {
-        let Some(SortedSet(_, ranked)) = self.get_sorted_set(key)? else {
+    fn geosearch(&self, key: &Bytes, query: &GeoSearchQuery) -> Result<Vec<GeoMatch>> {
+        let Some(set) = self.get_sorted_set(key)? else {
             return Ok(Vec::new());
         };
-        let is_within_radius = |location: &&RankedItem| -> bool {
-            haversine_dist_meters(from_coords, score_to_coord(location.score as u64)) < radius
+        let origin = match &query.origin {
+            GeoOrigin::Coord(coord) => *coord,
+            GeoOrigin::Member(member) => match set.0.get(member) {
+                Some(score) => score_to_coord(*score as u64),
+                None => return Err(MEMBER_NOT_FOUND),
+            },
+        };
+        let shape = match &query.shape {
+            GeoShape::Radius(radius) => GeoShape::Radius(radius * query.unit.meters()),
+            GeoShape::Box(width, height) => {
+                GeoShape::Box(width * query.unit.meters(), height * query.unit.meters())
+            }
+            GeoShape::Polygon(vertices) => GeoShape::Polygon(vertices.clone()),
+        };
+
+        // only scan the geohash boxes around the shape, in ascending order of score
+        let items = shape_score_ranges(&shape, origin)
+            .into_iter()
+            .flat_map(|(min, max)| set.score_range(min as f64, max as f64));
+        let mut matches = Vec::new();
+        for item in items {
+            let coord = score_to_coord(item.score as u64);
+            let Some(dist) = shape_distance_meters(&shape, origin, coord) else {
+                continue;
+            };
+            matches.push(GeoMatch {
+                member: item.member.clone(),
+                dist: dist / query.unit.meters(),
+                hash: item.score as u64,
+                coord,
+            });
+            if query.any && query.count == Some(matches.len()) {
+                break;
+            }
+        }
+
+        // matches are sorted by default when only the closest ones are returned
+        let order = match (query.order, query.count) {
+            (None, Some(_)) if !query.any => Some(GeoOrder::Asc),
+            (order, _) => order,
         };
-        let members_within_radius = ranked
+        match order {
+            Some(GeoOrder::Asc) => matches.sort_by(|a, b| a.dist.total_cmp(&b.dist)),
+            Some(GeoOrder::Desc) => matches.sort_by(|a, b| b.dist.total_cmp(&a.dist)),
+            None => {}
+        }
+        if let Some(count) = query.count {
+            matches.truncate(count);
+        }
+
+        Ok(matches)
+    }
+
+    fn geosearchstore(
+        &mut self,
+        dest: Bytes,
+        src: &Bytes,
+        query: &GeoSearchQuery,
+        store_dist: bool,
+    ) -> Result<i64> {
+        let matches = self.geosearch(src, query)?;
+        let len = matches.len();
+        if matches.is_empty() {
+            self.data.remove(&dest);
+        } else {
+            let set = matches
+                .into_iter()
+                .map(|m| match store_dist {
+                    true => (m.member, m.dist),
+                    false => (m.member, m.hash as f64),
+                })
+                .collect();
+            let object = RedisObject::new(RedisDataType::SortedSet(set));
+            self.data.insert(dest, object);
+        }
+
+        Ok(len.try_into().unwrap_or_default())
+    }
+}
+
+const MEMBER_NOT_FOUND: Bytes = Bytes::from_static(b"ERR could not decode requested zset member");
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    /// Search by checking every member of the set, instead of the geohash boxes around the shape
+    fn full_scan(storage: &MemoryStorage, key: &Bytes, query: &GeoSearchQuery) -> Vec<GeoMatch> {
+        let set = storage.get_sorted_set(key).unwrap().unwrap();
+        let origin = match &query.origin {
+            GeoOrigin::Coord(coord) => *coord,
+            GeoOrigin::Member(member) => score_to_coord(set.0[member] as u64),
+        };
+        let meters = query.unit.meters();
+        let shape = match &query.shape {
+            GeoShape::Radius(radius) => GeoShape::Radius(radius * meters),
+            GeoShape::Box(width, height) => GeoShape::Box(width * meters, height * meters),
+            GeoShape::Polygon(vertices) => GeoShape::Polygon(vertices.clone()),
+        };
+        let mut matches: Vec<_> = set
+            .1
+            .iter()
+            .filter_map(|item| {
+                let coord = score_to_coord(item.score as u64);
+                let dist = shape_distance_meters(&shape, origin, coord)?;
+                Some(GeoMatch {
+                    member: item.member.clone(),
+                    dist: dist / meters,
+                    hash: item.score as u64,
+                    coord,
+                })
+            })
+            .collect();
+
+        if query.any {
+            matches.truncate(query.count.unwrap());
+        }
+        match (query.order, query.count.is_some() && !query.any) {
+            (Some(GeoOrder::Asc), _) | (None, true) => {
+                matches.sort_by(|a, b| a.dist.total_cmp(&b.dist))
+            }
+            (Some(GeoOrder::Desc), _) => matches.sort_by(|a, b| b.dist.total_cmp(&a.dist)),
+            (None, false) => {}
+        }
+        matches.truncate(query.count.unwrap_or(usize::MAX));
+        matches
+    }
+
+    #[test]
+    fn geosearch_matches_full_scan() {
+        let mut rng = StdRng::seed_from_u64(24);
+        let random_coord = |rng: &mut StdRng| {
+            (
+                rng.random_range(-180.0..180.0),
+                rng.random_range(-85.0..85.0),
+            )
+        };
+        // points spread over the globe, and clusters of various sizes
+        let mut points: Vec<_> = (0..2000).map(|_| random_coord(&mut rng)).collect();
+        for spread in [0.001, 0.1, 5.0] {
+            let center: (f64, f64) = random_coord(&mut rng);
+            points.extend((0..1000).map(|_| {
+                let lon = center.0 + rng.random_range(-spread..spread);
+                let lat = center.1 + rng.random_range(-spread..spread);
+                ((lon + 540.0) % 360.0 - 180.0, lat.clamp(-85.0, 85.0))
+            }));
+        }
+        let mut storage = MemoryStorage::default();
+        let key = Bytes::from_static(b"points");
+        let members: Vec<_> = points
             .iter()
-            .filter(is_within_radius)
-            .map(|item| item.member.clone())
+            .enumerate()
+            .map(|(i, coord)| (*coord, Bytes::from(i.to_string())))
             .collect();
+        storage.geoadd(key.clone(), members).unwrap();
+
+        for _ in 0..500 {
+            // search around a member, or near one
+            let i = rng.random_range(0..points.len());
+            let origin = match rng.random_bool(0.5) {
+                true => GeoOrigin::Member(Bytes::from(i.to_string())),
+                false => GeoOrigin::Coord((
+                    (points[i].0 + rng.random_range(-0.01..0.01)).clamp(-180.0, 180.0),
+                    (points[i].1 + rng.random_range(-0.01..0.01)).clamp(-85.0, 85.0),
+                )),
+            };
+            let unit = [
+                GeoUnit::Meters,
+                GeoUnit::Kilometers,
+                GeoUnit::Feet,
+                GeoUnit::Miles,
+            ][rng.random_range(0..4)];
+            // from 1 meter to the whole planet
+            let size = 10f64.powf(rng.random_range(0.0..7.5)) / unit.meters();
+            let shape = match rng.random_range(0..3) {
+                0 => GeoShape::Radius(size),
+                1 => GeoShape::Box(size, size * rng.random_range(0.2..5.0)),
+                _ => {
+                    let degrees = (size * unit.meters() / 111_000.0).min(60.0);
+                    let (lon, lat) = points[i];
+                    let vertices =
+                        [(-1.0, -1.0), (1.0, -0.5), (0.5, 1.0), (-0.8, 0.6)].map(|(dx, dy)| {
+                            (
+                                (lon + dx * degrees + 540.0) % 360.0 - 180.0,
+                                (lat + dy * degrees).clamp(-85.0, 85.0),
+                            )
+                        });
+                    GeoShape::Polygon(vertices.to_vec())
+                }
+            };
+            let count = rng.random_bool(0.5).then(|| rng.random_range(1..20));
+            let query = GeoSearchQuery {
+                origin,
+                shape,
+                unit,
+                order: [None, Some(GeoOrder::Asc), Some(GeoOrder::Desc)][rng.random_range(0..3)],
+                count,
+                any: count.is_some() && rng.random_bool(0.5),
+            };
 
-        Ok(members_within_radius)
+            assert_eq!(
+                storage.geosearch(&key, &query).unwrap(),
+                full_scan(&storage, &key, &query),
+                "{query:?}"
+            );
+        }
     }
 }
@@ -1,5 +1,7 @@
 //! Geo conversion utilies
 
+use super::GeoShape;
+
 const MIN_LATITUDE: f64 = -85.05112878;
 const MAX_LATITUDE: f64 = 85.05112878;
 const MIN_LONGITUDE: f64 = -180.0;
@@ -8,19 +10,25 @@ const MAX_LONGITUDE: f64 = 180.0;
 const LATITUDE_RANGE: f64 = MAX_LATITUDE - MIN_LATITUDE;
 const LONGITUDE_RANGE: f64 = MAX_LONGITUDE - MIN_LONGITUDE;
 
-const NORMALIZE: f64 = 67_108_864.0 as f64; // 2^26
+const NORMALIZE: f64 = 67_108_864.0; // 2^26
+const MAX_STEP: u32 = 26;
+
+const EARTH_RADIUS_METERS: f64 = 6372797.560856;
+/// Half of the Earth's circumference at the equator, in the Web Mercator projection
+const MERCATOR_MAX: f64 = 20037726.37;
+
+/// Standard geohash alphabet (base32 without `a`, `i`, `l`, and `o`)
+const GEOHASH_ALPHABET: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
 
 pub fn validate_lon(lon: f64) -> bool {
-    MIN_LONGITUDE <= lon && lon <= MAX_LONGITUDE
+    (MIN_LONGITUDE..=MAX_LONGITUDE).contains(&lon)
 }
 
 pub fn validate_lat(lat: f64) -> bool {
-    MIN_LATITUDE <= lat && lat <= MAX_LATITUDE
+    (MIN_LATITUDE..=MAX_LATITUDE).contains(&lat)
 }
 
 pub fn haversine_dist_meters(origin: (f64, f64), destination: (f64, f64)) -> f64 {
-    const EARTH_RADIUS_METERS: f64 = 6372797.560856;
-
     let lat1 = origin.1.to_radians();
     let lat2 = destination.1.to_radians();
     let d_lat = lat2 - lat1;
@@ -31,6 +39,260 @@ pub fn haversine_dist_meters(origin: (f64, f64), destination: (f64, f64)) -> f64
     EARTH_RADIUS_METERS * c
 }
 
+/// Get the distance in meters from the origin to the point, if the point is within the shape
+/// (with dimensions in meters) centered on the origin
+pub fn shape_distance_meters(
+    shape: &GeoShape,
+    origin: (f64, f64),
+    point: (f64, f64),
+) -> Option<f64> {
+    match *shape {
+        GeoShape::Radius(radius) => {
+            let dist = haversine_dist_meters(origin, point);
+            (dist <= radius).then_some(dist)
+        }
+        GeoShape::Box(width, height) => {
+            // distance along the meridian, then along the point's parallel
+            let lat_dist = haversine_dist_meters((point.0, origin.1), point);
+            if lat_dist > height / 2.0 {
+                return None;
+            }
+            let lon_dist = haversine_dist_meters((origin.0, point.1), point);
+            if lon_dist > width / 2.0 {
+                return None;
+            }
+            Some(haversine_dist_meters(origin, point))
+        }
+        GeoShape::Polygon(ref vertices) => {
+            polygon_contains(vertices, point).then(|| haversine_dist_meters(origin, point))
+        }
+    }
+}
+
+/// Whether the point is within the polygon, with straight edges between the vertices on a
+/// longitude and latitude map
+fn polygon_contains(vertices: &[(f64, f64)], (lon, lat): (f64, f64)) -> bool {
+    let vertices = unwrap_lons(vertices);
+    let min_lon = vertices
+        .clone()
+        .map(|(lon, _)| lon)
+        .fold(f64::INFINITY, f64::min);
+    // the point's longitude within the unwrapped longitudes of the polygon
+    let lon = min_lon + (lon - min_lon).rem_euclid(LONGITUDE_RANGE);
+
+    // count the edges crossing the point's parallel to the east of the point
+    let next = vertices.clone().skip(1).chain(vertices.clone().take(1));
+    let mut inside = false;
+    for ((lon1, lat1), (lon2, lat2)) in vertices.zip(next) {
+        if (lat1 > lat) != (lat2 > lat) && lon < lon1 + (lat - lat1) * (lon2 - lon1) / (lat2 - lat1)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Unwrap the longitudes of the polygon's vertices, so that edges crossing the antimeridian
+/// continue past -180 or 180 degrees instead of going around the globe
+fn unwrap_lons(vertices: &[(f64, f64)]) -> impl Iterator<Item = (f64, f64)> + Clone + '_ {
+    let first = vertices.first().map_or(0.0, |(lon, _)| *lon);
+    vertices.iter().scan(first, |prev, &(lon, lat)| {
+        *prev += (lon - *prev - MIN_LONGITUDE).rem_euclid(LONGITUDE_RANGE) + MIN_LONGITUDE;
+        Some((*prev, lat))
+    })
+}
+
+/// Get the score ranges (with an inclusive start and exclusive end) that contain every point
+/// within the shape (with dimensions in meters) centered on the origin, in ascending order.
+///
+/// As in Redis, the geohash precision is estimated from the size of the shape, so that the
+/// geohash box containing the origin and its 8 neighbours cover the whole shape. Polygons use
+/// the box containing the middle of their bounds instead of the origin.
+pub fn shape_score_ranges(shape: &GeoShape, origin: (f64, f64)) -> Vec<(u64, u64)> {
+    let (lon_bounds, lat_bounds) = bounding_box(shape, origin);
+    let (origin, radius) = match *shape {
+        GeoShape::Radius(radius) => (origin, radius),
+        GeoShape::Box(width, height) => (origin, (width / 2.0).hypot(height / 2.0)),
+        GeoShape::Polygon(ref vertices) => {
+            let middle = (
+                (lon_bounds.0 + lon_bounds.1) / 2.0,
+                (lat_bounds.0 + lat_bounds.1) / 2.0,
+            );
+            let radius = vertices
+                .iter()
+                .map(|vertex| haversine_dist_meters(middle, *vertex))
+                .fold(0.0, f64::max);
+            (middle, radius)
+        }
+    };
+    let mut step = estimate_step(radius, origin.1);
+    let mut center = coord_to_score(origin) >> (2 * (MAX_STEP - step));
+
+    // the neighbouring boxes may not reach the edges of the bounding box when the origin is
+    // close to an edge of its box or near the poles, so use larger boxes
+    while step > 1 && !neighbours_reach(center, step, (lon_bounds, lat_bounds)) {
+        step -= 1;
+        center >>= 2;
+    }
+
+    // skip the neighbours in directions where the center box already covers the bounding box
+    let (lon, lat) = box_area(center, step);
+    let skip = |dx: i8, dy: i8| {
+        step >= 2
+            && ((dx < 0 && lon.0 < lon_bounds.0)
+                || (dx > 0 && lon.1 > lon_bounds.1)
+                || (dy < 0 && lat.0 < lat_bounds.0)
+                || (dy > 0 && lat.1 > lat_bounds.1))
+    };
+    let shift = 2 * (MAX_STEP - step);
+    let mut ranges: Vec<_> = [-1, 0, 1]
+        .into_iter()
+        .flat_map(|dx| [-1, 0, 1].map(|dy| (dx, dy)))
+        .filter(|&(dx, dy)| !skip(dx, dy))
+        .map(|(dx, dy)| {
+            let bits = move_box(center, step, dx, dy);
+            (bits << shift, (bits + 1) << shift)
+        })
+        .collect();
+    // neighbours can be the same box when the boxes wrap around the globe
+    ranges.sort_unstable();
+    ranges.dedup();
+    ranges
+}
+
+/// Estimate the geohash precision (the number of bits for each coordinate) where the boxes
+/// are large enough for a search of the radius
+fn estimate_step(radius: f64, lat: f64) -> u32 {
+    if radius == 0.0 {
+        return MAX_STEP;
+    }
+    let mut step: i32 = 1;
+    let mut range = radius;
+    while range < MERCATOR_MAX {
+        range *= 2.0;
+        step += 1;
+    }
+    step -= 2;
+    // boxes are narrower towards the poles
+    if lat.abs() > 66.0 {
+        step -= 1;
+        if lat.abs() > 80.0 {
+            step -= 1;
+        }
+    }
+    step.clamp(1, MAX_STEP as i32) as u32
+}
+
+/// Get the longitude and latitude bounds of a box around the shape centered on the origin
+fn bounding_box(shape: &GeoShape, (lon, lat): (f64, f64)) -> ((f64, f64), (f64, f64)) {
+    let (half_width, half_height, is_box) = match *shape {
+        GeoShape::Radius(radius) => (radius, radius, false),
+        GeoShape::Box(width, height) => (width / 2.0, height / 2.0, true),
+        GeoShape::Polygon(ref vertices) => return polygon_bounds(vertices),
+    };
+    let lat_delta = (half_height / EARTH_RADIUS_METERS).to_degrees();
+    // the longitude span is widest at the latitude closest to the pole
+    let polar_lat = (lat.abs() + lat_delta).to_radians();
+    let lon_delta = match is_box {
+        // widest point along the polar parallel, within half the width by great circle
+        true => (half_width / 2.0 / EARTH_RADIUS_METERS).sin() / polar_lat.cos(),
+        // widest point of a spherical cap
+        false => (half_width / EARTH_RADIUS_METERS).sin() / lat.to_radians().cos(),
+    };
+    let lon_delta = match (polar_lat.to_degrees() < 90.0, lon_delta < 1.0) {
+        (true, true) if is_box => 2.0 * lon_delta.asin().to_degrees(),
+        (true, true) => lon_delta.asin().to_degrees(),
+        // the shape covers a pole, or every longitude
+        _ => MAX_LONGITUDE,
+    };
+    (
+        (lon - lon_delta, lon + lon_delta),
+        (
+            (lat - lat_delta).max(MIN_LATITUDE),
+            (lat + lat_delta).min(MAX_LATITUDE),
+        ),
+    )
+}
+
+/// Get the longitude and latitude bounds of the polygon. The longitude bounds are unwrapped
+/// around the middle of the polygon, so they may extend past the antimeridian.
+fn polygon_bounds(vertices: &[(f64, f64)]) -> ((f64, f64), (f64, f64)) {
+    let mut lon_bounds = (f64::INFINITY, f64::NEG_INFINITY);
+    let mut lat_bounds = (f64::INFINITY, f64::NEG_INFINITY);
+    for (lon, lat) in unwrap_lons(vertices) {
+        lon_bounds = (lon_bounds.0.min(lon), lon_bounds.1.max(lon));
+        lat_bounds = (lat_bounds.0.min(lat), lat_bounds.1.max(lat));
+    }
+    // move the middle back within the map
+    let middle = (lon_bounds.0 + lon_bounds.1) / 2.0;
+    let shift = (middle - MIN_LONGITUDE).rem_euclid(LONGITUDE_RANGE) + MIN_LONGITUDE - middle;
+    ((lon_bounds.0 + shift, lon_bounds.1 + shift), lat_bounds)
+}
+
+/// Whether the neighbours of the geohash box reach the edges of the bounds
+fn neighbours_reach(
+    center: u64,
+    step: u32,
+    (lon_bounds, lat_bounds): ((f64, f64), (f64, f64)),
+) -> bool {
+    let (lon, lat) = box_area(center, step);
+    let east = box_area(move_box(center, step, 1, 0), step).0;
+    let west = box_area(move_box(center, step, -1, 0), step).0;
+    let north = box_area(move_box(center, step, 0, 1), step).1;
+    let south = box_area(move_box(center, step, 0, -1), step).1;
+
+    // neighbours wrap around the antimeridian, but not around the poles
+    let east_edge = if east.0 < lon.0 {
+        east.1 + LONGITUDE_RANGE
+    } else {
+        east.1
+    };
+    let west_edge = if west.1 > lon.1 {
+        west.0 - LONGITUDE_RANGE
+    } else {
+        west.0
+    };
+    let north_edge = if north.0 < lat.0 { lat.1 } else { north.1 };
+    let south_edge = if south.1 > lat.1 { lat.0 } else { south.0 };
+    east_edge >= lon_bounds.1
+        && west_edge <= lon_bounds.0
+        && north_edge >= lat_bounds.1
+        && south_edge <= lat_bounds.0
+}
+
+/// Move the geohash box by the number of boxes east (`dx`) and north (`dy`), wrapping around
+/// the edges of the map
+fn move_box(bits: u64, step: u32, dx: i8, dy: i8) -> u64 {
+    let lon_mask = 0xAAAAAAAAAAAAAAAA >> (64 - 2 * step);
+    let lat_mask = 0x5555555555555555 >> (64 - 2 * step);
+    // adding 1 to the bits of a coordinate carries across the interleaved bits of the other
+    let shift = |coord: u64, other_mask: u64, d: i8| match d {
+        1 => (coord | other_mask).wrapping_add(1),
+        -1 => (coord & !other_mask).wrapping_sub(1),
+        _ => coord,
+    };
+    let lon = shift(bits & lon_mask, lat_mask, dx) & lon_mask;
+    let lat = shift(bits & lat_mask, lon_mask, dy) & lat_mask;
+    lon | lat
+}
+
+/// Get the longitude and latitude bounds of the geohash box
+fn box_area(bits: u64, step: u32) -> ((f64, f64), (f64, f64)) {
+    let scale = (1u64 << step) as f64;
+    let lon = compact_u64_to_u32(bits >> 1) as f64;
+    let lat = compact_u64_to_u32(bits) as f64;
+    (
+        (
+            MIN_LONGITUDE + LONGITUDE_RANGE * lon / scale,
+            MIN_LONGITUDE + LONGITUDE_RANGE * (lon + 1.0) / scale,
+        ),
+        (
+            MIN_LATITUDE + LATITUDE_RANGE * lat / scale,
+            MIN_LATITUDE + LATITUDE_RANGE * (lat + 1.0) / scale,
+        ),
+    )
+}
+
 pub fn coord_to_score((lon, lat): (f64, f64)) -> u64 {
     // normalize and truncate
     let normalized_lon = (NORMALIZE * (lon - MIN_LONGITUDE) / LONGITUDE_RANGE) as u32;
@@ -69,6 +331,27 @@ pub fn score_to_coord(score: u64) -> (f64, f64) {
     (lon, lat)
 }
 
+/// Convert a score into the standard 11-character geohash string. Scores are encoded with the
+/// Web Mercator latitude limits, so the coordinates are re-encoded with the standard latitude
+/// limits of +/-90 degrees.
+pub fn score_to_geohash(score: u64) -> String {
+    let (lon, lat) = score_to_coord(score);
+    let normalized_lon = (NORMALIZE * (lon - MIN_LONGITUDE) / LONGITUDE_RANGE) as u32;
+    let normalized_lat = (NORMALIZE * (lat + 90.0) / 180.0) as u32;
+    let hash = spread_u32_to_u64(normalized_lat) | (spread_u32_to_u64(normalized_lon) << 1);
+
+    // 52 bits make 10 characters of 5 bits, and the last character is padded with zeros
+    (0..11)
+        .map(|i| {
+            let idx = match i {
+                10 => 0,
+                i => (hash >> (52 - (i + 1) * 5)) & 0x1f,
+            };
+            GEOHASH_ALPHABET[idx as usize] as char
+        })
+        .collect()
+}
+
 /// Spreads a 32-bit integer to a 64-bit integer by inserting
 /// 32 zero bits in-between.
 ///
@@ -76,8 +359,8 @@ pub fn score_to_coord(score: u64) -> (f64, f64) {
 ///
 /// After spread:  `0   x1  ...   0   x16  ... 0  x31  0  x32`
 fn spread_u32_to_u64(v: u32) -> u64 {
-    //  Ensure only lower 32 bits are non-zero, and cast to 64.
-    let mut v: u64 = (v & 0xFFFFFFFF).into();
+    // Cast to 64 bits (upper 32 bits are zero)
+    let mut v: u64 = v.into();
 
     // # Bitwise operations to spread 32 bits into 64 bits with zeros in-between
     v = (v | (v << 16)) & 0x0000FFFF0000FFFF;
@@ -112,6 +395,8 @@ fn compact_u64_to_u32(v: u64) -> u32 {
 
 #[cfg(test)]
 mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
     use super::*;
 
     #[test]
@@ -128,4 +413,134 @@ mod tests {
         let ny_score = 1791873974549446;
         assert_eq!(coord_to_score(ny_coord), ny_score);
     }
+
+    #[test]
+    fn geohash() {
+        assert_eq!(score_to_geohash(3479099956230698), "sqc8b49rny0");
+        assert_eq!(score_to_geohash(3479447370796909), "sqdtr74hyu0");
+    }
+
+    #[test]
+    fn shape_ranges_cover_shape() {
+        let mut rng = StdRng::seed_from_u64(24);
+        let mut origins: Vec<_> = (0..2000)
+            .map(|_| {
+                let lon = rng.random_range(MIN_LONGITUDE..MAX_LONGITUDE);
+                (lon, rng.random_range(MIN_LATITUDE..MAX_LATITUDE))
+            })
+            .collect();
+        origins.extend([(179.99, 0.0), (-179.99, 45.0), (0.0, 85.0), (30.0, -84.9)]);
+        // shapes crossing the antimeridian
+        origins.extend((0..200).map(|i| {
+            let lon = if i % 2 == 0 { 179.9 } else { -179.9 };
+            (lon, rng.random_range(-60.0..60.0))
+        }));
+
+        for origin in origins {
+            // from 1 meter to the whole planet
+            let size = 10f64.powf(rng.random_range(0.0..7.5));
+            let shape = match rng.random_range(0..3) {
+                0 => GeoShape::Radius(size),
+                1 => GeoShape::Box(size, size * rng.random_range(0.2..5.0)),
+                _ => {
+                    // star-shaped polygon around the origin
+                    let degrees = (size / EARTH_RADIUS_METERS).to_degrees();
+                    let mut angles: Vec<f64> = (0..rng.random_range(3..8))
+                        .map(|_| rng.random_range(0.0..std::f64::consts::TAU))
+                        .collect();
+                    angles.sort_by(f64::total_cmp);
+                    let vertices = angles.into_iter().map(|angle| {
+                        let dist = degrees * rng.random_range(0.2..1.0);
+                        let lon = origin.0 + dist * angle.cos();
+                        let lat = origin.1 + dist * angle.sin();
+                        (
+                            (lon - MIN_LONGITUDE).rem_euclid(LONGITUDE_RANGE) + MIN_LONGITUDE,
+                            lat.clamp(MIN_LATITUDE, MAX_LATITUDE),
+                        )
+                    });
+                    GeoShape::Polygon(vertices.collect())
+                }
+            };
+            let ranges = shape_score_ranges(&shape, origin);
+            assert!(ranges.windows(2).all(|pair| pair[0].1 <= pair[1].0));
+
+            // points around the shape, up to twice as far as its extent
+            let (lon_bounds, lat_bounds) = bounding_box(&shape, origin);
+            let middle = (
+                (lon_bounds.0 + lon_bounds.1) / 2.0,
+                (lat_bounds.0 + lat_bounds.1) / 2.0,
+            );
+            let lon_spread = (lon_bounds.1 - lon_bounds.0).min(LONGITUDE_RANGE);
+            let lat_spread = (lat_bounds.1 - lat_bounds.0).max(1e-9);
+            for _ in 0..200 {
+                let lon = middle.0 + rng.random_range(-lon_spread..=lon_spread);
+                let lon = (lon - MIN_LONGITUDE).rem_euclid(LONGITUDE_RANGE) + MIN_LONGITUDE;
+                let lat = middle.1 + rng.random_range(-lat_spread..=lat_spread);
+                let lat = lat.clamp(MIN_LATITUDE, MAX_LATITUDE - 1e-9);
+                let score = coord_to_score((lon, lat));
+                if shape_distance_meters(&shape, origin, score_to_coord(score)).is_some() {
+                    assert!(
+                        ranges
+                            .iter()
+                            .any(|(min, max)| (*min..*max).contains(&score)),
+                        "{shape:?} around {origin:?} misses {:?}",
+                        score_to_coord(score)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn polygons() {
+        let triangle = [(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)];
+        assert!(polygon_contains(&triangle, (2.0, 2.0)));
+        assert!(!polygon_contains(&triangle, (6.0, 6.0)));
+        assert!(!polygon_contains(&triangle, (-1.0, 1.0)));
+
+        let concave = [
+            (0.0, 0.0),
+            (3.0, 0.0),
+            (3.0, 3.0),
+            (2.0, 3.0),
+            (2.0, 1.0),
+            (1.0, 1.0),
+            (1.0, 3.0),
+            (0.0, 3.0),
+        ];
+        assert!(polygon_contains(&concave, (0.5, 2.0)));
+        assert!(polygon_contains(&concave, (1.5, 0.5)));
+        assert!(!polygon_contains(&concave, (1.5, 2.0)));
+
+        // edges take the short way across the antimeridian
+        let square = [
+            (170.0, -10.0),
+            (-170.0, -10.0),
+            (-170.0, 10.0),
+            (170.0, 10.0),
+        ];
+        assert!(polygon_contains(&square, (179.0, 0.0)));
+        assert!(polygon_contains(&square, (-175.0, 5.0)));
+        assert!(!polygon_contains(&square, (0.0, 0.0)));
+        assert_eq!(polygon_bounds(&square), ((-190.0, -170.0), (-10.0, 10.0)));
+    }
+
+    #[test]
+    fn shapes() {
+        let palermo = (13.361389, 38.115556);
+        let catania = (15.087269, 37.502669);
+        let dist = haversine_dist_meters(palermo, catania);
+        assert!((dist - 166274.15).abs() < 1.0);
+
+        let radius = |radius| shape_distance_meters(&GeoShape::Radius(radius), palermo, catania);
+        assert_eq!(radius(200_000.0), Some(dist));
+        assert_eq!(radius(dist), Some(dist));
+        assert_eq!(radius(100_000.0), None);
+
+        // catania is ~151km east and ~68km south of palermo
+        let bybox = |w, h| shape_distance_meters(&GeoShape::Box(w, h), palermo, catania);
+        assert_eq!(bybox(400_000.0, 400_000.0), Some(dist));
+        assert_eq!(bybox(400_000.0, 100_000.0), None);
+        assert_eq!(bybox(200_000.0, 400_000.0), None);
+    }
 }
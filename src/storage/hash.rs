@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+
+use bytes::Bytes;
+
+use super::{
+    random_sample, unix_time_millis, ExpiryCondition, MemoryStorage, RedisDataType, RedisObject,
+    StorageResult as Result,
+};
+
+/// Hash storage: HashMap of `field -> value`, where each field can have its own expiration
+#[derive(Debug, Default, PartialEq)]
+pub struct Hash(pub(super) HashMap<Bytes, HashField>);
+
+/// A hash field value with an optional expiration
+#[derive(Debug, PartialEq)]
+pub struct HashField {
+    pub(super) value: Bytes,
+    /// Expiration in Unix time milliseconds
+    pub(super) expiration: Option<u64>,
+}
+
+impl HashField {
+    pub(super) fn new(value: Bytes) -> Self {
+        Self {
+            value,
+            expiration: None,
+        }
+    }
+
+    pub(super) fn is_current(&self) -> bool {
+        if let Some(expiration) = self.expiration {
+            unix_time_millis() <= expiration
+        } else {
+            true
+        }
+    }
+}
+
+impl Hash {
+    /// Get the field if it exists and isn't expired
+    fn get(&self, field: &Bytes) -> Option<&HashField> {
+        self.0.get(field).filter(|f| f.is_current())
+    }
+
+    /// Get a mutable reference to the field if it exists. Removes the field and returns `None` if expired.
+    fn get_mut(&mut self, field: &Bytes) -> Option<&mut HashField> {
+        if self.0.get(field).is_some_and(|f| !f.is_current()) {
+            self.0.remove(field);
+        }
+        self.0.get_mut(field)
+    }
+
+    /// Iterate over all fields that aren't expired
+    fn iter(&self) -> impl Iterator<Item = (&Bytes, &HashField)> {
+        self.0.iter().filter(|(_, f)| f.is_current())
+    }
+
+    /// Whether the hash has any fields that aren't expired
+    pub(super) fn has_current_fields(&self) -> bool {
+        self.0.values().any(HashField::is_current)
+    }
+
+    /// Remove all expired fields, and return the number of fields removed
+    pub(super) fn remove_expired(&mut self) -> usize {
+        let len = self.0.len();
+        self.0.retain(|_, f| f.is_current());
+        len - self.0.len()
+    }
+}
+
+/// Hash interface
+pub trait HashStorage {
+    /// Set the given fields in the hash. Returns the number of new fields added.
+    fn hset(&mut self, key: Bytes, fields: Vec<(Bytes, Bytes)>) -> Result<i64>;
+    /// Set the field only if it doesn't exist yet. Returns whether the field was set.
+    fn hsetnx(&mut self, key: Bytes, field: Bytes, value: Bytes) -> Result<bool>;
+    fn hget(&self, key: &Bytes, field: &Bytes) -> Result<Option<Bytes>>;
+    fn hmget(&self, key: &Bytes, fields: Vec<Bytes>) -> Result<Vec<Option<Bytes>>>;
+    fn hdel(&mut self, key: &Bytes, fields: Vec<Bytes>) -> Result<i64>;
+    fn hexists(&self, key: &Bytes, field: &Bytes) -> Result<bool>;
+    fn hlen(&self, key: &Bytes) -> Result<i64>;
+    fn hkeys(&self, key: &Bytes) -> Result<Vec<Bytes>>;
+    fn hvals(&self, key: &Bytes) -> Result<Vec<Bytes>>;
+    fn hgetall(&self, key: &Bytes) -> Result<Vec<(Bytes, Bytes)>>;
+    /// Get random fields and their values: up to `count` distinct fields if positive, or
+    /// `-count` fields that may repeat if negative
+    fn hrandfield(&self, key: &Bytes, count: i64) -> Result<Vec<(Bytes, Bytes)>>;
+    /// Increment the integer value of the field, creating it if needed. Returns the new value.
+    fn hincrby(&mut self, key: Bytes, field: Bytes, incr: i64) -> Result<i64>;
+    /// Increment the float value of the field, creating it if needed. Returns the new value.
+    fn hincrbyfloat(&mut self, key: Bytes, field: Bytes, incr: f64) -> Result<f64>;
+    fn hstrlen(&self, key: &Bytes, field: &Bytes) -> Result<i64>;
+    /// Set the expiration of the given fields, in Unix time milliseconds. Returns for each field:
+    /// `-2` if missing, `0` if the condition wasn't met, `1` if set, or `2` if deleted (expiration
+    /// is in the past).
+    fn hexpire(
+        &mut self,
+        key: &Bytes,
+        expires_at: u64,
+        condition: Option<ExpiryCondition>,
+        fields: Vec<Bytes>,
+    ) -> Result<Vec<i64>>;
+    /// Get the remaining TTL in milliseconds of the given fields. Returns `-2` for missing
+    /// fields, and `-1` for fields without an expiration.
+    fn hpttl(&self, key: &Bytes, fields: Vec<Bytes>) -> Result<Vec<i64>>;
+    /// Remove the expiration of the given fields. Returns for each field: `-2` if missing,
+    /// `-1` if it had no expiration, or `1` if the expiration was removed.
+    fn hpersist(&mut self, key: &Bytes, fields: Vec<Bytes>) -> Result<Vec<i64>>;
+}
+
+impl HashStorage for MemoryStorage {
+    fn hset(&mut self, key: Bytes, fields: Vec<(Bytes, Bytes)>) -> Result<i64> {
+        let hash = self.get_hash_entry(key)?;
+        let num_added = fields
+            .into_iter()
+            .map(|(field, value)| hash.0.insert(field, HashField::new(value)))
+            .filter(|old| old.as_ref().is_none_or(|f| !f.is_current()))
+            .count();
+        Ok(num_added.try_into().unwrap_or_default())
+    }
+
+    fn hsetnx(&mut self, key: Bytes, field: Bytes, value: Bytes) -> Result<bool> {
+        let hash = self.get_hash_entry(key)?;
+        if hash.get(&field).is_some() {
+            return Ok(false);
+        }
+        hash.0.insert(field, HashField::new(value));
+        Ok(true)
+    }
+
+    fn hget(&self, key: &Bytes, field: &Bytes) -> Result<Option<Bytes>> {
+        Ok(self
+            .get_hash(key)?
+            .and_then(|hash| hash.get(field))
+            .map(|f| f.value.clone()))
+    }
+
+    fn hmget(&self, key: &Bytes, fields: Vec<Bytes>) -> Result<Vec<Option<Bytes>>> {
+        let Some(hash) = self.get_hash(key)? else {
+            return Ok(vec![None; fields.len()]);
+        };
+        Ok(fields
+            .iter()
+            .map(|f| hash.get(f).map(|f| f.value.clone()))
+            .collect())
+    }
+
+    fn hdel(&mut self, key: &Bytes, fields: Vec<Bytes>) -> Result<i64> {
+        let Some(hash) = self.get_hash_mut(key)? else {
+            return Ok(0);
+        };
+
+        let num_removed = fields
+            .iter()
+            .map(|f| hash.0.remove(f))
+            .filter(|removed| removed.as_ref().is_some_and(HashField::is_current))
+            .count();
+        if !hash.has_current_fields() {
+            self.data.remove(key);
+        }
+
+        Ok(num_removed.try_into().unwrap_or_default())
+    }
+
+    fn hexists(&self, key: &Bytes, field: &Bytes) -> Result<bool> {
+        Ok(self
+            .get_hash(key)?
+            .is_some_and(|hash| hash.get(field).is_some()))
+    }
+
+    fn hlen(&self, key: &Bytes) -> Result<i64> {
+        Ok(match self.get_hash(key)? {
+            Some(hash) => hash.iter().count().try_into().unwrap_or_default(),
+            None => 0,
+        })
+    }
+
+    fn hkeys(&self, key: &Bytes) -> Result<Vec<Bytes>> {
+        Ok(match self.get_hash(key)? {
+            Some(hash) => hash.iter().map(|(field, _)| field.clone()).collect(),
+            None => Vec::new(),
+        })
+    }
+
+    fn hvals(&self, key: &Bytes) -> Result<Vec<Bytes>> {
+        Ok(match self.get_hash(key)? {
+            Some(hash) => hash.iter().map(|(_, f)| f.value.clone()).collect(),
+            None => Vec::new(),
+        })
+    }
+
+    fn hgetall(&self, key: &Bytes) -> Result<Vec<(Bytes, Bytes)>> {
+        Ok(match self.get_hash(key)? {
+            Some(hash) => hash
+                .iter()
+                .map(|(field, f)| (field.clone(), f.value.clone()))
+                .collect(),
+            None => Vec::new(),
+        })
+    }
+
+    fn hrandfield(&self, key: &Bytes, count: i64) -> Result<Vec<(Bytes, Bytes)>> {
+        let Some(hash) = self.get_hash(key)? else {
+            return Ok(Vec::new());
+        };
+        let sample = random_sample(hash.iter(), count);
+        Ok(sample
+            .into_iter()
+            .map(|(field, f)| (field.clone(), f.value.clone()))
+            .collect())
+    }
+
+    fn hincrby(&mut self, key: Bytes, field: Bytes, incr: i64) -> Result<i64> {
+        let hash = self.get_hash_entry(key)?;
+        let Some(hash_field) = hash.get_mut(&field) else {
+            hash.0
+                .insert(field, HashField::new(Bytes::from(incr.to_string())));
+            return Ok(incr);
+        };
+        let current: i64 = std::str::from_utf8(&hash_field.value)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or(NOT_INTEGER)?;
+        let new_value = current.checked_add(incr).ok_or(OVERFLOW)?;
+        hash_field.value = Bytes::from(new_value.to_string());
+
+        Ok(new_value)
+    }
+
+    fn hincrbyfloat(&mut self, key: Bytes, field: Bytes, incr: f64) -> Result<f64> {
+        let hash = self.get_hash_entry(key)?;
+        let Some(hash_field) = hash.get_mut(&field) else {
+            hash.0
+                .insert(field, HashField::new(Bytes::from(incr.to_string())));
+            return Ok(incr);
+        };
+        let current: f64 = std::str::from_utf8(&hash_field.value)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or(NOT_FLOAT)?;
+        let new_value = current + incr;
+        if !new_value.is_finite() {
+            return Err(NAN_OR_INFINITY);
+        }
+        hash_field.value = Bytes::from(new_value.to_string());
+
+        Ok(new_value)
+    }
+
+    fn hstrlen(&self, key: &Bytes, field: &Bytes) -> Result<i64> {
+        Ok(match self.get_hash(key)?.and_then(|hash| hash.get(field)) {
+            Some(f) => f.value.len().try_into().unwrap_or_default(),
+            None => 0,
+        })
+    }
+
+    fn hexpire(
+        &mut self,
+        key: &Bytes,
+        expires_at: u64,
+        condition: Option<ExpiryCondition>,
+        fields: Vec<Bytes>,
+    ) -> Result<Vec<i64>> {
+        let Some(hash) = self.get_hash_mut(key)? else {
+            return Ok(vec![-2; fields.len()]);
+        };
+
+        let is_expired = expires_at <= unix_time_millis();
+        let results = fields
+            .iter()
+            .map(|field| {
+                let Some(hash_field) = hash.get_mut(field) else {
+                    return -2;
+                };
+                if condition.is_some_and(|c| !c.is_met(hash_field.expiration, expires_at)) {
+                    return 0;
+                }
+                if is_expired {
+                    hash.0.remove(field);
+                    return 2;
+                }
+                hash_field.expiration = Some(expires_at);
+                1
+            })
+            .collect();
+        if !hash.has_current_fields() {
+            self.data.remove(key);
+        }
+
+        Ok(results)
+    }
+
+    fn hpttl(&self, key: &Bytes, fields: Vec<Bytes>) -> Result<Vec<i64>> {
+        let Some(hash) = self.get_hash(key)? else {
+            return Ok(vec![-2; fields.len()]);
+        };
+        let now = unix_time_millis();
+        Ok(fields
+            .iter()
+            .map(|field| match hash.get(field) {
+                Some(HashField {
+                    expiration: Some(expiration),
+                    ..
+                }) => expiration
+                    .saturating_sub(now)
+                    .try_into()
+                    .unwrap_or_default(),
+                Some(_) => -1,
+                None => -2,
+            })
+            .collect())
+    }
+
+    fn hpersist(&mut self, key: &Bytes, fields: Vec<Bytes>) -> Result<Vec<i64>> {
+        let Some(hash) = self.get_hash_mut(key)? else {
+            return Ok(vec![-2; fields.len()]);
+        };
+        let results = fields
+            .iter()
+            .map(|field| match hash.get_mut(field) {
+                Some(hash_field) => match hash_field.expiration.take() {
+                    Some(_) => 1,
+                    None => -1,
+                },
+                None => -2,
+            })
+            .collect();
+        if !hash.has_current_fields() {
+            self.data.remove(key);
+        }
+
+        Ok(results)
+    }
+}
+
+const NOT_HASH: Bytes = Bytes::from_static(b"Not a hash");
+const NOT_INTEGER: Bytes = Bytes::from_static(b"ERR hash value is not an integer");
+const NOT_FLOAT: Bytes = Bytes::from_static(b"ERR hash value is not a float");
+pub(super) const OVERFLOW: Bytes = Bytes::from_static(b"ERR increment or decrement would overflow");
+pub(super) const NAN_OR_INFINITY: Bytes =
+    Bytes::from_static(b"ERR increment would produce NaN or Infinity");
+
+impl MemoryStorage {
+    fn get_hash(&self, key: &Bytes) -> Result<Option<&Hash>> {
+        match self.get(key) {
+            Some(RedisDataType::Hash(hash)) => Ok(Some(hash)),
+            Some(_) => Err(NOT_HASH),
+            None => Ok(None),
+        }
+    }
+
+    fn get_hash_mut(&mut self, key: &Bytes) -> Result<Option<&mut Hash>> {
+        match self.get_mut(key) {
+            Some(RedisDataType::Hash(hash)) => Ok(Some(hash)),
+            Some(_) => Err(NOT_HASH),
+            None => Ok(None),
+        }
+    }
+
+    fn get_hash_entry(&mut self, key: Bytes) -> Result<&mut Hash> {
+        let entry = self.get_entry_with_default(key, RedisObject::new_hash);
+        let RedisDataType::Hash(ref mut hash) = entry.data else {
+            return Err(NOT_HASH);
+        };
+        Ok(hash)
+    }
+}
+
+impl RedisObject {
+    fn new_hash() -> Self {
+        Self::new(RedisDataType::Hash(Hash::default()))
+    }
+}
@@ -0,0 +1,128 @@
+use bytes::{Bytes, BytesMut};
+
+use super::{string::NOT_STRING, MemoryStorage, RedisDataType, StorageResult as Result};
+
+mod hll_utils;
+use hll_utils::{Encoding, REGISTERS};
+
+/// HyperLogLog interface. HyperLogLogs are stored as strings in the same format as Redis.
+pub trait HyperLogLogStorage {
+    /// Add the elements to the HyperLogLog, creating it if needed. Returns whether the key was
+    /// created or any of its registers changed.
+    fn pfadd(&mut self, key: Bytes, elems: Vec<Bytes>) -> Result<bool>;
+    /// Estimate the number of unique elements in the union of the HyperLogLogs. The cardinality
+    /// of a single HyperLogLog is cached in its header until it changes.
+    fn pfcount(&mut self, keys: &[Bytes]) -> Result<i64>;
+    /// Merge the HyperLogLogs into the destination key, including its current value if it exists
+    fn pfmerge(&mut self, dest: Bytes, keys: &[Bytes]) -> Result<()>;
+}
+
+impl HyperLogLogStorage for MemoryStorage {
+    fn pfadd(&mut self, key: Bytes, elems: Vec<Bytes>) -> Result<bool> {
+        let is_new = self.get(&key).is_none();
+        let hll = self.get_string_entry(key)?;
+        if is_new {
+            *hll = Bytes::from(hll_utils::encode(&[0; REGISTERS], true));
+        }
+
+        let is_updated = match hll_utils::encoding(hll).ok_or(NOT_HLL)? {
+            Encoding::Dense => {
+                let mut buf = BytesMut::from(std::mem::take(hll));
+                let mut is_updated = false;
+                for elem in elems {
+                    let (idx, value) = hll_utils::hash_element(&elem);
+                    is_updated |= hll_utils::dense_set_max(&mut buf, idx, value);
+                }
+                if is_updated {
+                    hll_utils::set_cached_count(&mut buf, None);
+                }
+                *hll = buf.freeze();
+                is_updated
+            }
+            Encoding::Sparse => {
+                let mut registers = hll_utils::registers(hll).ok_or(CORRUPTED)?;
+                let mut is_updated = false;
+                for elem in elems {
+                    let (idx, value) = hll_utils::hash_element(&elem);
+                    if registers[idx] < value {
+                        registers[idx] = value;
+                        is_updated = true;
+                    }
+                }
+                if is_updated {
+                    *hll = Bytes::from(hll_utils::encode(&registers, true));
+                }
+                is_updated
+            }
+        };
+
+        Ok(is_new || is_updated)
+    }
+
+    fn pfcount(&mut self, keys: &[Bytes]) -> Result<i64> {
+        if let [key] = keys {
+            let Some(hll) = self.get_hll_mut(key)? else {
+                return Ok(0);
+            };
+            if let Some(count) = hll_utils::cached_count(hll) {
+                return Ok(count.try_into().unwrap_or_default());
+            }
+            let count = hll_utils::count(&hll_utils::registers(hll).ok_or(CORRUPTED)?);
+            let mut buf = BytesMut::from(std::mem::take(hll));
+            hll_utils::set_cached_count(&mut buf, Some(count));
+            *hll = buf.freeze();
+            return Ok(count.try_into().unwrap_or_default());
+        }
+
+        let (registers, _) = self.merge_hlls(keys)?;
+        Ok(hll_utils::count(&registers).try_into().unwrap_or_default())
+    }
+
+    fn pfmerge(&mut self, dest: Bytes, keys: &[Bytes]) -> Result<()> {
+        let mut all_keys = Vec::with_capacity(keys.len() + 1);
+        all_keys.push(dest.clone());
+        all_keys.extend_from_slice(keys);
+        let (registers, is_dense) = self.merge_hlls(&all_keys)?;
+
+        let hll = self.get_string_entry(dest)?;
+        *hll = Bytes::from(hll_utils::encode(&registers, !is_dense));
+        Ok(())
+    }
+}
+
+const NOT_HLL: Bytes =
+    Bytes::from_static(b"WRONGTYPE Key is not a valid HyperLogLog string value.");
+const CORRUPTED: Bytes = Bytes::from_static(b"INVALIDOBJ Corrupted HLL object detected");
+
+impl MemoryStorage {
+    /// Get a mutable reference to the HyperLogLog string at the key, validating its header
+    fn get_hll_mut(&mut self, key: &Bytes) -> Result<Option<&mut Bytes>> {
+        match self.get_mut(key) {
+            Some(RedisDataType::String(hll)) => match hll_utils::encoding(hll) {
+                Some(_) => Ok(Some(hll)),
+                None => Err(NOT_HLL),
+            },
+            Some(RedisDataType::Integer(_)) => Err(NOT_HLL),
+            Some(_) => Err(NOT_STRING),
+            None => Ok(None),
+        }
+    }
+
+    /// Merge the registers of the HyperLogLogs (skipping missing keys), taking the maximum
+    /// value of each register. Also returns whether any of them used the dense encoding.
+    fn merge_hlls(&self, keys: &[Bytes]) -> Result<(Vec<u8>, bool)> {
+        let mut merged = vec![0; REGISTERS];
+        let mut is_dense = false;
+        for key in keys {
+            let Some(hll) = self.get_string(key)? else {
+                continue;
+            };
+            is_dense |= hll_utils::encoding(&hll).ok_or(NOT_HLL)? == Encoding::Dense;
+            let registers = hll_utils::registers(&hll).ok_or(CORRUPTED)?;
+            for (max, value) in merged.iter_mut().zip(registers) {
+                *max = value.max(*max);
+            }
+        }
+        Ok((merged, is_dense))
+    }
+}
@@ -0,0 +1,343 @@
+//! HyperLogLog utilities, using the same string format as Redis: a 16-byte header
+//! (`HYLL` magic, encoding, and cached cardinality) followed by the registers in either the
+//! dense or sparse encoding.
+
+/// Number of registers
+pub const REGISTERS: usize = 1 << P;
+/// Number of bits of the hash used for the register index
+const P: u32 = 14;
+/// Number of bits of the hash used to count the run of zeros
+const Q: u32 = 64 - P;
+/// Bits per register in the dense encoding
+const REGISTER_BITS: usize = 6;
+const REGISTER_MAX: u8 = (1 << REGISTER_BITS) - 1;
+
+const MAGIC: &[u8] = b"HYLL";
+const HEADER_LEN: usize = 16;
+const DENSE_LEN: usize = HEADER_LEN + REGISTERS * REGISTER_BITS / 8;
+const ENCODING_DENSE: u8 = 0;
+const ENCODING_SPARSE: u8 = 1;
+/// Offset of the cached cardinality in the header
+const CARD_OFFSET: usize = 8;
+
+/// Maximum length of a sparse HyperLogLog before converting to dense (Redis's default
+/// `hll-sparse-max-bytes`)
+const SPARSE_MAX_LEN: usize = 3000;
+/// Maximum register value in the sparse encoding
+const SPARSE_MAX_VALUE: u8 = 32;
+/// Sparse opcode for a run of 1-64 zero registers: `00xxxxxx`
+const SPARSE_ZERO_MAX_LEN: usize = 64;
+/// Sparse opcode for a run of 1-16384 zero registers: `01xxxxxx yyyyyyyy`
+const SPARSE_XZERO_BIT: u8 = 0x40;
+const SPARSE_XZERO_MAX_LEN: usize = 16384;
+/// Sparse opcode for a run of 1-4 registers with the value 1-32: `1vvvvvxx`
+const SPARSE_VAL_BIT: u8 = 0x80;
+const SPARSE_VAL_MAX_LEN: usize = 4;
+
+/// The encoding of a HyperLogLog string
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Dense,
+    Sparse,
+}
+
+/// Get the encoding of the string, or `None` if it isn't a valid HyperLogLog
+pub fn encoding(hll: &[u8]) -> Option<Encoding> {
+    if hll.len() < HEADER_LEN || !hll.starts_with(MAGIC) {
+        return None;
+    }
+    match hll[4] {
+        ENCODING_DENSE if hll.len() == DENSE_LEN => Some(Encoding::Dense),
+        ENCODING_SPARSE => Some(Encoding::Sparse),
+        _ => None,
+    }
+}
+
+/// Hash the element into a register index and the value to set (the length of the run of
+/// zeros in the rest of the hash, plus one)
+pub fn hash_element(elem: &[u8]) -> (usize, u8) {
+    let hash = murmur_hash_64a(elem, 0xadc83b19);
+    let index = hash as usize & (REGISTERS - 1);
+    // set the last bit so the count is at most Q + 1
+    let hash = (hash >> P) | (1 << Q);
+    (index, hash.trailing_zeros() as u8 + 1)
+}
+
+/// Decode all the registers of the HyperLogLog. Returns `None` if the data is corrupted.
+pub fn registers(hll: &[u8]) -> Option<Vec<u8>> {
+    match encoding(hll)? {
+        Encoding::Dense => Some((0..REGISTERS).map(|idx| dense_get(hll, idx)).collect()),
+        Encoding::Sparse => {
+            let mut registers = Vec::with_capacity(REGISTERS);
+            let mut data = hll[HEADER_LEN..].iter();
+            while let Some(&op) = data.next() {
+                let (value, len) = if op & SPARSE_VAL_BIT != 0 {
+                    (((op >> 2) & 0x1F) + 1, (op & 0x03) as usize + 1)
+                } else if op & SPARSE_XZERO_BIT != 0 {
+                    let next = *data.next()?;
+                    (0, (((op & 0x3F) as usize) << 8 | next as usize) + 1)
+                } else {
+                    (0, (op & 0x3F) as usize + 1)
+                };
+                if registers.len() + len > REGISTERS {
+                    return None;
+                }
+                registers.resize(registers.len() + len, value);
+            }
+            (registers.len() == REGISTERS).then_some(registers)
+        }
+    }
+}
+
+/// Encode the registers into a new HyperLogLog, using the sparse encoding if requested and
+/// the registers fit within its limits
+pub fn encode(registers: &[u8], sparse: bool) -> Vec<u8> {
+    if sparse {
+        if let Some(hll) = encode_sparse(registers) {
+            return hll;
+        }
+    }
+    let mut hll = header(ENCODING_DENSE);
+    hll.resize(DENSE_LEN, 0);
+    for (idx, value) in registers.iter().enumerate() {
+        dense_set(&mut hll, idx, *value);
+    }
+    hll
+}
+
+/// Set the register of a dense HyperLogLog if the value is greater than the current value.
+/// Returns whether the register was updated.
+pub fn dense_set_max(hll: &mut [u8], idx: usize, value: u8) -> bool {
+    if dense_get(hll, idx) >= value {
+        return false;
+    }
+    dense_set(hll, idx, value);
+    true
+}
+
+/// Get the cached cardinality, if it's valid
+pub fn cached_count(hll: &[u8]) -> Option<u64> {
+    let card: [u8; 8] = hll[CARD_OFFSET..HEADER_LEN].try_into().ok()?;
+    // the most significant bit marks the cache as invalid
+    (card[7] & 0x80 == 0).then(|| u64::from_le_bytes(card))
+}
+
+/// Set the cached cardinality, or invalidate the cache if `None`
+pub fn set_cached_count(hll: &mut [u8], count: Option<u64>) {
+    match count {
+        Some(count) => hll[CARD_OFFSET..HEADER_LEN].copy_from_slice(&count.to_le_bytes()),
+        None => hll[HEADER_LEN - 1] |= 0x80,
+    }
+}
+
+/// Estimate the cardinality from the registers, using the improved estimator from Otmar
+/// Ertl's "New cardinality estimation algorithms for HyperLogLog sketches" (same as Redis)
+pub fn count(registers: &[u8]) -> u64 {
+    const ALPHA_INF: f64 = 0.721_347_520_444_481_7;
+    let m = REGISTERS as f64;
+    let mut histogram = [0u32; 64];
+    for value in registers {
+        histogram[*value as usize] += 1;
+    }
+
+    let mut z = m * tau((m - f64::from(histogram[Q as usize + 1])) / m);
+    for count in histogram[1..=Q as usize].iter().rev() {
+        z += f64::from(*count);
+        z *= 0.5;
+    }
+    z += m * sigma(f64::from(histogram[0]) / m);
+
+    (ALPHA_INF * m * m / z).round() as u64
+}
+
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+    let (mut y, mut z) = (1.0, x);
+    loop {
+        x *= x;
+        let z_prev = z;
+        z += x * y;
+        y += y;
+        if z == z_prev {
+            return z;
+        }
+    }
+}
+
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+    let (mut y, mut z) = (1.0, 1.0 - x);
+    loop {
+        x = x.sqrt();
+        let z_prev = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+        if z == z_prev {
+            return z / 3.0;
+        }
+    }
+}
+
+/// Create a header with the given encoding and an invalid cached cardinality
+fn header(encoding: u8) -> Vec<u8> {
+    let mut hll = Vec::with_capacity(HEADER_LEN);
+    hll.extend_from_slice(MAGIC);
+    hll.push(encoding);
+    hll.resize(HEADER_LEN, 0);
+    set_cached_count(&mut hll, None);
+    hll
+}
+
+/// Encode the registers with the sparse encoding. Returns `None` if a register value is too
+/// large, or the result would exceed the maximum sparse length.
+fn encode_sparse(registers: &[u8]) -> Option<Vec<u8>> {
+    let mut hll = header(ENCODING_SPARSE);
+    let mut idx = 0;
+    while idx < registers.len() {
+        let value = registers[idx];
+        if value > SPARSE_MAX_VALUE {
+            return None;
+        }
+        let run_len = registers[idx..].iter().take_while(|v| **v == value).count();
+        let max_len = match value {
+            0 if run_len > SPARSE_ZERO_MAX_LEN => SPARSE_XZERO_MAX_LEN,
+            0 => SPARSE_ZERO_MAX_LEN,
+            _ => SPARSE_VAL_MAX_LEN,
+        };
+        let len = run_len.min(max_len);
+        match value {
+            0 if len > SPARSE_ZERO_MAX_LEN => {
+                hll.push(SPARSE_XZERO_BIT | ((len - 1) >> 8) as u8);
+                hll.push((len - 1) as u8);
+            }
+            0 => hll.push((len - 1) as u8),
+            _ => hll.push(SPARSE_VAL_BIT | (value - 1) << 2 | (len - 1) as u8),
+        }
+        if hll.len() > SPARSE_MAX_LEN {
+            return None;
+        }
+        idx += len;
+    }
+    Some(hll)
+}
+
+/// Get the register of a dense HyperLogLog. Registers are packed 6 bits each, starting from
+/// the least significant bit of each byte.
+fn dense_get(hll: &[u8], idx: usize) -> u8 {
+    let bit = idx * REGISTER_BITS;
+    let (byte, shift) = (HEADER_LEN + bit / 8, bit % 8);
+    let low = hll[byte] >> shift;
+    let high = hll
+        .get(byte + 1)
+        .map_or(0, |b| b.checked_shl(8 - shift as u32).unwrap_or(0));
+    (low | high) & REGISTER_MAX
+}
+
+fn dense_set(hll: &mut [u8], idx: usize, value: u8) {
+    let bit = idx * REGISTER_BITS;
+    let (byte, shift) = (HEADER_LEN + bit / 8, bit % 8);
+    hll[byte] &= !(REGISTER_MAX << shift);
+    hll[byte] |= value << shift;
+    if shift + REGISTER_BITS > 8 {
+        let shift = 8 - shift;
+        hll[byte + 1] &= !(REGISTER_MAX >> shift);
+        hll[byte + 1] |= value >> shift;
+    }
+}
+
+/// MurmurHash2, 64-bit version by Austin Appleby (the variant used by Redis)
+fn murmur_hash_64a(key: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4a7935bd1e995;
+    const R: u32 = 47;
+
+    let mut h = seed ^ (key.len() as u64).wrapping_mul(M);
+    let mut chunks = key.chunks_exact(8);
+    for chunk in chunks.by_ref() {
+        let mut k = u64::from_le_bytes(chunk.try_into().expect("chunk should be 8 bytes"));
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (idx, byte) in tail.iter().enumerate() {
+            h ^= u64::from(*byte) << (idx * 8);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sparse_and_dense_encoding() {
+        let empty = encode(&[0; REGISTERS], true);
+        assert_eq!(empty.len(), HEADER_LEN + 2);
+        assert_eq!(encoding(&empty), Some(Encoding::Sparse));
+        assert_eq!(registers(&empty), Some(vec![0; REGISTERS]));
+
+        let mut values = vec![0; REGISTERS];
+        values[0] = 3;
+        values[1] = 3;
+        values[100] = 32;
+        values[REGISTERS - 1] = 1;
+        let sparse = encode(&values, true);
+        assert_eq!(encoding(&sparse), Some(Encoding::Sparse));
+        assert_eq!(registers(&sparse).as_ref(), Some(&values));
+
+        let dense = encode(&values, false);
+        assert_eq!(encoding(&dense), Some(Encoding::Dense));
+        assert_eq!(registers(&dense).as_ref(), Some(&values));
+
+        // values over 32 can't be stored in the sparse encoding
+        values[200] = 33;
+        assert_eq!(encoding(&encode(&values, true)), Some(Encoding::Dense));
+
+        let mut dense = encode(&[0; REGISTERS], false);
+        assert!(dense_set_max(&mut dense, 5, 10));
+        assert!(!dense_set_max(&mut dense, 5, 9));
+        assert_eq!(dense_get(&dense, 5), 10);
+        assert!(dense_set_max(&mut dense, REGISTERS - 1, REGISTER_MAX));
+        assert_eq!(dense_get(&dense, REGISTERS - 1), REGISTER_MAX);
+
+        assert_eq!(encoding(b"HYLL"), None);
+        assert_eq!(registers(&sparse[..sparse.len() - 1]), None);
+    }
+
+    #[test]
+    fn cached_cardinality() {
+        let mut hll = encode(&[0; REGISTERS], true);
+        assert_eq!(cached_count(&hll), None);
+        set_cached_count(&mut hll, Some(12345));
+        assert_eq!(cached_count(&hll), Some(12345));
+        set_cached_count(&mut hll, None);
+        assert_eq!(cached_count(&hll), None);
+    }
+
+    #[test]
+    fn estimate_cardinality() {
+        assert_eq!(count(&[0; REGISTERS]), 0);
+
+        for num_elems in [10, 1000, 100_000] {
+            let mut values = vec![0; REGISTERS];
+            for elem in 0..num_elems {
+                let (idx, value) = hash_element(format!("elem:{elem}").as_bytes());
+                values[idx] = values[idx].max(value);
+            }
+            let error = (count(&values) as f64 - num_elems as f64).abs() / num_elems as f64;
+            assert!(error < 0.02, "error for {num_elems} elements was {error}");
+        }
+    }
+}
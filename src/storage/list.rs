@@ -4,7 +4,7 @@ use bytes::Bytes;
 
 use crate::storage::MemoryStorage;
 
-use super::{RedisDataType, RedisObject};
+use super::{string::normalize_range, RedisDataType, RedisObject, StorageResult};
 
 /// List interface
 pub trait ListStorage {
@@ -15,8 +15,62 @@ pub trait ListStorage {
         dir: ListDirection,
     ) -> Result<i64, Bytes>;
     fn pop(&mut self, key: &Bytes, dir: ListDirection, count: i64) -> Option<Vec<Bytes>>;
+    /// Pop up to `count` elements from the first of the keys that holds a list. Returns the key
+    /// and the popped elements, or `None` if none of the lists exist.
+    fn mpop(
+        &mut self,
+        keys: &[Bytes],
+        dir: ListDirection,
+        count: usize,
+    ) -> StorageResult<Option<(Bytes, Vec<Bytes>)>>;
+    /// Pop an element from the source list and push it to the destination list. Returns
+    /// the moved element, or `None` if the source list doesn't exist.
+    fn lmove(
+        &mut self,
+        src: &Bytes,
+        dest: Bytes,
+        from: ListDirection,
+        to: ListDirection,
+    ) -> StorageResult<Option<Bytes>>;
     fn llen(&self, key: &Bytes) -> i64;
     fn lrange(&self, key: &Bytes, start: i64, stop: i64) -> Vec<Bytes>;
+    /// Push the elements only if the list exists. Returns the new length, or `0` if the list
+    /// doesn't exist.
+    fn pushx(
+        &mut self,
+        key: &Bytes,
+        elems: VecDeque<Bytes>,
+        dir: ListDirection,
+    ) -> StorageResult<i64>;
+    /// Get the element at the index. Negative indexes count from the end of the list.
+    fn lindex(&self, key: &Bytes, index: i64) -> StorageResult<Option<Bytes>>;
+    /// Replace the element at the index. Negative indexes count from the end of the list.
+    fn lset(&mut self, key: &Bytes, index: i64, elem: Bytes) -> StorageResult<()>;
+    /// Insert the element before or after the first occurrence of the pivot. Returns the new
+    /// length, `-1` if the pivot wasn't found, or `0` if the list doesn't exist.
+    fn linsert(
+        &mut self,
+        key: &Bytes,
+        before: bool,
+        pivot: &Bytes,
+        elem: Bytes,
+    ) -> StorageResult<i64>;
+    /// Remove occurrences of the element: the first `count` if positive, the last `count` if
+    /// negative, or all if zero. Returns the number removed.
+    fn lrem(&mut self, key: &Bytes, count: i64, elem: &Bytes) -> StorageResult<i64>;
+    /// Trim the list to the inclusive start and stop indexes, deleting it if empty
+    fn ltrim(&mut self, key: &Bytes, start: i64, stop: i64) -> StorageResult<()>;
+    /// Find the indexes of the element. Starts from the `rank`-th match (from the end if
+    /// negative), returns up to `count` matches (`0` for all), and scans at most `maxlen`
+    /// elements (`0` for the whole list).
+    fn lpos(
+        &self,
+        key: &Bytes,
+        elem: &Bytes,
+        rank: i64,
+        count: usize,
+        maxlen: usize,
+    ) -> StorageResult<Vec<i64>>;
 }
 
 /// Direction for push/pop operations
@@ -47,7 +101,7 @@ impl ListStorage for MemoryStorage {
             }
             Ok(vec.len().try_into().unwrap_or_default())
         } else {
-            Err(Bytes::from_static(b"Not a list"))
+            Err(NOT_LIST)
         }
     }
 
@@ -74,6 +128,51 @@ impl ListStorage for MemoryStorage {
         Some(elems)
     }
 
+    fn mpop(
+        &mut self,
+        keys: &[Bytes],
+        dir: ListDirection,
+        count: usize,
+    ) -> StorageResult<Option<(Bytes, Vec<Bytes>)>> {
+        for key in keys {
+            if self.get_list(key)?.is_some() {
+                let count = count.try_into().unwrap_or(i64::MAX);
+                let elems = self.pop(key, dir, count).unwrap_or_default();
+                return Ok(Some((key.clone(), elems)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn lmove(
+        &mut self,
+        src: &Bytes,
+        dest: Bytes,
+        from: ListDirection,
+        to: ListDirection,
+    ) -> StorageResult<Option<Bytes>> {
+        // check the destination type before modifying the source
+        if src != &dest {
+            self.get_list(&dest)?;
+        }
+        let Some(list) = self.get_list_mut(src)? else {
+            return Ok(None);
+        };
+        let popped = match from {
+            ListDirection::Left => list.pop_front(),
+            ListDirection::Right => list.pop_back(),
+        };
+        let Some(elem) = popped else {
+            return Ok(None);
+        };
+        self.push(dest, VecDeque::from([elem.clone()]), to)?;
+        if self.get_list(src)?.is_some_and(VecDeque::is_empty) {
+            self.data.remove(src);
+        }
+
+        Ok(Some(elem))
+    }
+
     fn llen(&self, key: &Bytes) -> i64 {
         if let Some(RedisDataType::List(list)) = self.get(key) {
             list.len().try_into().unwrap_or_default()
@@ -86,32 +185,164 @@ impl ListStorage for MemoryStorage {
         let Some(RedisDataType::List(list)) = self.get(key) else {
             return Vec::new();
         };
+        match normalize_range(start, stop, list.len()) {
+            Some((beg, end)) => list.range(beg..=end).cloned().collect(),
+            None => Vec::new(),
+        }
+    }
 
-        let beg: usize = if start < 0 {
-            list.len()
-                .checked_add_signed(start.try_into().unwrap_or_default())
-                .unwrap_or_default()
+    fn pushx(
+        &mut self,
+        key: &Bytes,
+        elems: VecDeque<Bytes>,
+        dir: ListDirection,
+    ) -> StorageResult<i64> {
+        if self.get_list(key)?.is_none() {
+            return Ok(0);
+        }
+        self.push(key.clone(), elems, dir)
+    }
+
+    fn lindex(&self, key: &Bytes, index: i64) -> StorageResult<Option<Bytes>> {
+        let Some(list) = self.get_list(key)? else {
+            return Ok(None);
+        };
+        Ok(list_index(list.len(), index).map(|idx| list[idx].clone()))
+    }
+
+    fn lset(&mut self, key: &Bytes, index: i64, elem: Bytes) -> StorageResult<()> {
+        let Some(list) = self.get_list_mut(key)? else {
+            return Err(Bytes::from_static(b"ERR no such key"));
+        };
+        let Some(idx) = list_index(list.len(), index) else {
+            return Err(Bytes::from_static(b"ERR index out of range"));
+        };
+        list[idx] = elem;
+        Ok(())
+    }
+
+    fn linsert(
+        &mut self,
+        key: &Bytes,
+        before: bool,
+        pivot: &Bytes,
+        elem: Bytes,
+    ) -> StorageResult<i64> {
+        let Some(list) = self.get_list_mut(key)? else {
+            return Ok(0);
+        };
+        let Some(pivot_idx) = list.iter().position(|e| e == pivot) else {
+            return Ok(-1);
+        };
+        list.insert(if before { pivot_idx } else { pivot_idx + 1 }, elem);
+        Ok(list.len().try_into().unwrap_or_default())
+    }
+
+    fn lrem(&mut self, key: &Bytes, count: i64, elem: &Bytes) -> StorageResult<i64> {
+        let Some(list) = self.get_list_mut(key)? else {
+            return Ok(0);
+        };
+        let max = match count {
+            0 => usize::MAX,
+            count => count.unsigned_abs().try_into().unwrap_or(usize::MAX),
+        };
+        let mut num_removed = 0;
+        if count >= 0 {
+            list.retain(|e| {
+                let remove = num_removed < max && e == elem;
+                num_removed += usize::from(remove);
+                !remove
+            });
         } else {
-            start as usize
+            let mut idx = list.len();
+            while idx > 0 && num_removed < max {
+                idx -= 1;
+                if list[idx] == elem {
+                    list.remove(idx);
+                    num_removed += 1;
+                }
+            }
+        }
+        if list.is_empty() {
+            self.data.remove(key);
+        }
+
+        Ok(num_removed.try_into().unwrap_or_default())
+    }
+
+    fn ltrim(&mut self, key: &Bytes, start: i64, stop: i64) -> StorageResult<()> {
+        let Some(list) = self.get_list_mut(key)? else {
+            return Ok(());
         };
-        if beg >= list.len() {
-            return Vec::new();
+        match normalize_range(start, stop, list.len()) {
+            Some((beg, end)) => {
+                list.truncate(end + 1);
+                list.drain(..beg);
+            }
+            None => list.clear(),
         }
+        if list.is_empty() {
+            self.data.remove(key);
+        }
+        Ok(())
+    }
 
-        let mut end: usize = if stop < 0 {
-            list.len()
-                .checked_add_signed(stop.try_into().unwrap_or_default())
-                .unwrap_or_default()
+    fn lpos(
+        &self,
+        key: &Bytes,
+        elem: &Bytes,
+        rank: i64,
+        count: usize,
+        maxlen: usize,
+    ) -> StorageResult<Vec<i64>> {
+        let Some(list) = self.get_list(key)? else {
+            return Ok(Vec::new());
+        };
+        let maxlen = if maxlen == 0 { list.len() } else { maxlen };
+        let count = if count == 0 { usize::MAX } else { count };
+        let skip = (rank.unsigned_abs() - 1).try_into().unwrap_or(usize::MAX);
+        let indexes: Box<dyn Iterator<Item = usize>> = if rank > 0 {
+            Box::new((0..list.len()).take(maxlen))
         } else {
-            stop as usize
+            Box::new((0..list.len()).rev().take(maxlen))
         };
-        if end >= list.len() {
-            end = list.len() - 1;
-        }
-        if beg > end {
-            return Vec::new();
+
+        Ok(indexes
+            .filter(|idx| list[*idx] == elem)
+            .skip(skip)
+            .take(count)
+            .map(|idx| idx.try_into().unwrap_or_default())
+            .collect())
+    }
+}
+
+const NOT_LIST: Bytes = Bytes::from_static(b"Not a list");
+
+/// Convert the index (negative indexes count from the end) into a valid index for a list
+/// of the given length
+fn list_index(len: usize, index: i64) -> Option<usize> {
+    let idx = if index < 0 {
+        len.checked_add_signed(index.try_into().ok()?)?
+    } else {
+        index.try_into().ok()?
+    };
+    (idx < len).then_some(idx)
+}
+
+impl MemoryStorage {
+    fn get_list(&self, key: &Bytes) -> StorageResult<Option<&VecDeque<Bytes>>> {
+        match self.get(key) {
+            Some(RedisDataType::List(list)) => Ok(Some(list)),
+            Some(_) => Err(NOT_LIST),
+            None => Ok(None),
         }
+    }
 
-        list.range(beg..=end).cloned().collect()
+    fn get_list_mut(&mut self, key: &Bytes) -> StorageResult<Option<&mut VecDeque<Bytes>>> {
+        match self.get_mut(key) {
+            Some(RedisDataType::List(list)) => Ok(Some(list)),
+            Some(_) => Err(NOT_LIST),
+            None => Ok(None),
+        }
     }
 }
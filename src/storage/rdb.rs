@@ -52,7 +52,7 @@ pub fn load_rdb_file(file_path: &Path) -> anyhow::Result<MemoryStorage> {
     // Load keys into storage
     let mut storage = MemoryStorage::default();
     for db in rdb.databases.into_iter() {
-        storage.data.extend(db.keys.into_iter());
+        storage.data.extend(db.keys);
     }
 
     Ok(storage)
@@ -15,3 +15,9 @@ pub const STRING_I32_FLAG: u8 = 0xC2;
 pub const TYPE_STRING_FLAG: u8 = 0x00;
 pub const TYPE_LIST_FLAG: u8 = 0x01;
 pub const TYPE_SET_FLAG: u8 = 0x02;
+pub const TYPE_HASH_FLAG: u8 = 0x04;
+/// Hash with field expirations (Unix time milliseconds)
+pub const TYPE_HASH_METADATA_FLAG: u8 = 0x18;
+
+pub const SIZE_U32_FLAG: u8 = 0x80;
+pub const SIZE_U64_FLAG: u8 = 0x81;
@@ -1,15 +1,16 @@
 use std::{
-    collections::{HashSet, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     io::Read,
-    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::bail;
 use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
 use bytes::{Bytes, BytesMut};
-use tokio::time::Instant;
 
-use crate::storage::{RedisDataType, RedisObject};
+use crate::storage::{
+    hash::{Hash, HashField},
+    unix_time_millis, RedisDataType, RedisObject,
+};
 
 use super::{constants, crc::Crc64Reader, Rdb, RdbDatabase};
 
@@ -92,24 +93,18 @@ impl<R: Read> RdbParser<R> {
 
         // Read keys, values, and expirations, discarding expired keys
         let mut keys = Vec::with_capacity(db_size);
-        let current_unix_time_millis = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
+        let current_unix_time_millis = unix_time_millis();
 
         while let Some((key, data, expires_at)) =
             next_key(&mut self.flag, &mut self.file, &mut self.buf)?
         {
-            let expiration = if let Some(expires_at) = expires_at {
-                if current_unix_time_millis > expires_at {
-                    continue; // discard expired key
-                }
-                let millis_to_expiration = expires_at - current_unix_time_millis;
-                Some(Instant::now() + Duration::from_millis(millis_to_expiration))
-            } else {
-                None
+            if expires_at.is_some_and(|expires_at| current_unix_time_millis > expires_at) {
+                continue; // discard expired key
+            }
+            let object = RedisObject {
+                expiration: expires_at,
+                data,
             };
-            let object = RedisObject { expiration, data };
             keys.push((key, object));
         }
 
@@ -188,6 +183,41 @@ fn read_key_value(
             }
             RedisDataType::Set(members)
         }
+        constants::TYPE_HASH_FLAG => {
+            let size = read_size(reader.read_u8()?, reader)?;
+            let mut fields = HashMap::with_capacity(size);
+            for _ in 0..size {
+                let n = read_length_encoded_string(reader, buf)?;
+                let field = buf.split_to(n).freeze();
+                let n = read_length_encoded_string(reader, buf)?;
+                fields.insert(field, HashField::new(buf.split_to(n).freeze()));
+            }
+            RedisDataType::Hash(Hash(fields))
+        }
+        constants::TYPE_HASH_METADATA_FLAG => {
+            // minimum expiration, and then each field's TTL relative to it (0 if no expiration)
+            let min_expires_at = reader.read_u64::<LittleEndian>()?;
+            let size = read_size(reader.read_u8()?, reader)?;
+            let current_unix_time_millis = unix_time_millis();
+
+            let mut fields = HashMap::with_capacity(size);
+            for _ in 0..size {
+                let ttl = read_size(reader.read_u8()?, reader)? as u64;
+                let n = read_length_encoded_string(reader, buf)?;
+                let field = buf.split_to(n).freeze();
+                let n = read_length_encoded_string(reader, buf)?;
+                let mut hash_field = HashField::new(buf.split_to(n).freeze());
+                if ttl > 0 {
+                    let expires_at = min_expires_at + ttl - 1;
+                    if current_unix_time_millis > expires_at {
+                        continue; // discard expired field
+                    }
+                    hash_field.expiration = Some(expires_at);
+                }
+                fields.insert(field, hash_field);
+            }
+            RedisDataType::Hash(Hash(fields))
+        }
         flag => bail!("unimplemented data type {flag:#X} in rdb file"),
     };
 
@@ -210,8 +240,12 @@ fn read_size(first_byte: u8, reader: &mut impl Read) -> anyhow::Result<usize> {
             let len_bytes = [first_byte & 0b00111111, reader.read_u8()?];
             u16::from_be_bytes(len_bytes) as usize
         }
-        // length is u32: next 4 bytes
-        0b10 => reader.read_u32::<BigEndian>()? as usize,
+        // length is u32 or u64: next 4 or 8 bytes
+        0b10 => match first_byte {
+            constants::SIZE_U32_FLAG => reader.read_u32::<BigEndian>()? as usize,
+            constants::SIZE_U64_FLAG => reader.read_u64::<BigEndian>()? as usize,
+            _ => bail!("invalid size flag {first_byte:#X}"),
+        },
         0b11 => bail!("expected size, got an encoded integer string"),
         _ => unreachable!(),
     };
@@ -224,7 +258,7 @@ fn read_length_encoded_string(reader: &mut impl Read, buf: &mut BytesMut) -> any
     // Check the first 2 bits
     match length_flag(first_byte) {
         // Get the length and read the whole string
-        0b00 | 0b01 | 0b10 => {
+        0b00..=0b10 => {
             let length = read_size(first_byte, reader)?;
             buf.resize(length, 0);
             reader.read_exact(&mut buf[..length])?;
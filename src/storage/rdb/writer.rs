@@ -6,7 +6,6 @@ use std::{
 use anyhow::bail;
 use byteorder::{BigEndian, LittleEndian, WriteBytesExt};
 use bytes::Bytes;
-use tokio::time::Instant;
 
 use super::{constants, crc::Crc64Writer, RedisDataType, RedisObject};
 
@@ -89,18 +88,9 @@ impl<W: Write> RdbWriter<W> {
         // Write database keys
         let mut db_size_check = 0;
         let mut expire_size_check = 0;
-        let unix_time_millis = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
-
         for (key, object) in keys {
             // Write expiration time (Unix epoch time millis)
-            let expires_at = object.expiration.map(|expiration| {
-                let expires_in_millis = (expiration - Instant::now()).as_millis() as u64;
-                unix_time_millis + expires_in_millis
-            });
-            if let Some(expires_at) = expires_at {
+            if let Some(expires_at) = object.expiration {
                 self.file.write_u8(constants::EXPIRY_U64_FLAG)?;
                 self.file.write_u64::<LittleEndian>(expires_at)?;
                 expire_size_check += 1;
@@ -108,9 +98,15 @@ impl<W: Write> RdbWriter<W> {
 
             // Write type flag
             let type_flag = match &object.data {
-                RedisDataType::String(_) => constants::TYPE_STRING_FLAG,
+                RedisDataType::String(_) | RedisDataType::Integer(_) => constants::TYPE_STRING_FLAG,
                 RedisDataType::List(_) => constants::TYPE_LIST_FLAG,
                 RedisDataType::Set(_) => constants::TYPE_SET_FLAG,
+                RedisDataType::Hash(hash) => {
+                    match hash.0.values().any(|f| f.expiration.is_some()) {
+                        true => constants::TYPE_HASH_METADATA_FLAG,
+                        false => constants::TYPE_HASH_FLAG,
+                    }
+                }
                 _ => todo!("data type not supported yet"),
             };
             self.file.write_u8(type_flag)?;
@@ -121,6 +117,9 @@ impl<W: Write> RdbWriter<W> {
                 RedisDataType::String(value) => {
                     write_string(&mut self.file, value)?;
                 }
+                RedisDataType::Integer(int) => {
+                    write_string_int(&mut self.file, *int)?;
+                }
                 RedisDataType::List(list) => {
                     write_size(&mut self.file, list.len())?;
                     for member in list {
@@ -133,6 +132,24 @@ impl<W: Write> RdbWriter<W> {
                         write_string(&mut self.file, member)?;
                     }
                 }
+                RedisDataType::Hash(hash) => {
+                    let min_expires_at = hash.0.values().filter_map(|f| f.expiration).min();
+
+                    // Write minimum expiration if any field has an expiration
+                    if let Some(min_expires_at) = min_expires_at {
+                        self.file.write_u64::<LittleEndian>(min_expires_at)?;
+                    }
+                    write_size(&mut self.file, hash.0.len())?;
+                    for (field, f) in &hash.0 {
+                        if let Some(min_expires_at) = min_expires_at {
+                            // TTL relative to the minimum expiration, or 0 if no expiration
+                            let ttl = f.expiration.map_or(0, |at| at - min_expires_at + 1);
+                            write_size(&mut self.file, ttl as usize)?;
+                        }
+                        write_string(&mut self.file, field)?;
+                        write_string(&mut self.file, &f.value)?;
+                    }
+                }
                 _ => todo!("data type not supported"),
             };
             db_size_check += 1;
@@ -168,7 +185,7 @@ fn write_size(writer: &mut impl Write, len: usize) -> io::Result<()> {
         len if len <= 0x3F => writer.write_u8(len as u8)?,
         len if len <= 0x3FFF => write_u16_size(writer, len as u16)?,
         len if len <= u32::MAX as usize => write_u32_size(writer, len as u32)?,
-        invalid_len => Err(io::Error::other(format!("Length {invalid_len} too long")))?,
+        len => write_u64_size(writer, len as u64)?,
     }
     Ok(())
 }
@@ -185,14 +202,20 @@ fn write_u16_size(writer: &mut impl Write, len: u16) -> io::Result<()> {
 
 /// For lengths up to 2^32 - 1 (u32::MAX)
 fn write_u32_size(writer: &mut impl Write, len: u32) -> io::Result<()> {
-    /// Indicates that a u32 follows
-    const FIRST_BYTE: u8 = 0x80;
-    writer.write_u8(FIRST_BYTE)?;
+    writer.write_u8(constants::SIZE_U32_FLAG)?;
     writer.write_u32::<BigEndian>(len)?;
 
     Ok(())
 }
 
+/// For lengths up to 2^64 - 1 (u64::MAX)
+fn write_u64_size(writer: &mut impl Write, len: u64) -> io::Result<()> {
+    writer.write_u8(constants::SIZE_U64_FLAG)?;
+    writer.write_u64::<BigEndian>(len)?;
+
+    Ok(())
+}
+
 /// Write an encoded integer string
 fn write_string_int(writer: &mut impl Write, val: i64) -> io::Result<()> {
     match val {
@@ -215,11 +238,15 @@ fn write_string_int(writer: &mut impl Write, val: i64) -> io::Result<()> {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::VecDeque;
+    use std::collections::{HashMap, VecDeque};
 
     use bytes::Buf;
 
     use super::super::parser::RdbParser;
+    use crate::storage::{
+        hash::{Hash, HashField},
+        unix_time_millis,
+    };
 
     use super::*;
 
@@ -241,6 +268,10 @@ mod tests {
         write_size(&mut buf, 16384)?;
         assert_eq!(buf, &[0x80, 0, 0, 0x40, 0x00], "len > 16383");
 
+        let mut buf = Vec::new();
+        write_size(&mut buf, 1 << 32)?;
+        assert_eq!(buf, &[0x81, 0, 0, 0, 0x01, 0, 0, 0, 0], "len > u32::MAX");
+
         Ok(())
     }
 
@@ -279,8 +310,8 @@ mod tests {
 
         let bar_key = Bytes::from("bar");
         let bar_val = Bytes::from("baz");
-        let bar_exp = 5000;
-        let bar_obj = RedisObject::new_with_ttl(
+        let bar_exp = unix_time_millis() + 5000;
+        let bar_obj = RedisObject::new_with_expiration(
             RedisDataType::List(VecDeque::from([foo_val.clone(), bar_val.clone()])),
             Some(bar_exp),
         );
@@ -309,7 +340,89 @@ mod tests {
             keys[1].1.data,
             RedisDataType::List(VecDeque::from([foo_val, bar_val]))
         );
-        assert!(keys[1].1.expiration.is_some());
+        assert_eq!(keys[1].1.expiration, Some(bar_exp));
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_and_parse_integer() -> anyhow::Result<()> {
+        let ints = [-5, 15500, 1 << 20, i64::MAX];
+        let objects: Vec<_> = ints
+            .iter()
+            .map(|int| {
+                let key = Bytes::from(format!("count:{int}"));
+                (key, RedisObject::new(RedisDataType::Integer(*int)))
+            })
+            .collect();
+
+        let mut buf = Vec::new();
+        let rdb_writer = RdbWriter::new(&mut buf);
+        rdb_writer.dump(objects.iter().map(|(k, o)| (k, o)).collect())?;
+
+        let rdb = RdbParser::new(buf.reader()).parse()?;
+        let keys = &rdb.databases[0].keys;
+        for (idx, int) in ints.iter().enumerate() {
+            assert_eq!(
+                keys[idx].1.data,
+                RedisDataType::String(Bytes::from(int.to_string()))
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_and_parse_hash() -> anyhow::Result<()> {
+        let key = Bytes::from("user:1");
+        let fields = || {
+            HashMap::from([
+                (Bytes::from("name"), HashField::new(Bytes::from("foo"))),
+                (Bytes::from("visits"), HashField::new(Bytes::from("42"))),
+            ])
+        };
+        let obj = RedisObject::new(RedisDataType::Hash(Hash(fields())));
+
+        let mut buf = Vec::new();
+        RdbWriter::new(&mut buf).dump(vec![(&key, &obj)])?;
+        let rdb = RdbParser::new(buf.reader()).parse()?;
+
+        let keys = &rdb.databases[0].keys;
+        assert_eq!(keys[0].0, key);
+        assert_eq!(keys[0].1.data, RedisDataType::Hash(Hash(fields())));
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_and_parse_hash_field_expiration() -> anyhow::Result<()> {
+        let key = Bytes::from("session:1");
+        let expires_at = unix_time_millis() + 5000;
+        let expiring = |value: &'static str, expiration: u64| HashField {
+            value: Bytes::from(value),
+            expiration: Some(expiration),
+        };
+        let fields = HashMap::from([
+            (Bytes::from("user"), HashField::new(Bytes::from("foo"))),
+            (Bytes::from("token"), expiring("abc", expires_at)),
+            (Bytes::from("nonce"), expiring("xyz", expires_at + 60_000)),
+        ]);
+        let obj = RedisObject::new(RedisDataType::Hash(Hash(fields)));
+
+        let mut buf = Vec::new();
+        RdbWriter::new(&mut buf).dump(vec![(&key, &obj)])?;
+        let rdb = RdbParser::new(buf.reader()).parse()?;
+
+        let RedisDataType::Hash(Hash(fields)) = &rdb.databases[0].keys[0].1.data else {
+            panic!("expected a hash");
+        };
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields[&Bytes::from("user")].expiration, None);
+        assert_eq!(fields[&Bytes::from("token")].expiration, Some(expires_at));
+        assert_eq!(
+            fields[&Bytes::from("nonce")].expiration,
+            Some(expires_at + 60_000)
+        );
 
         Ok(())
     }
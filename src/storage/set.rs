@@ -2,7 +2,7 @@ use std::collections::HashSet;
 
 use bytes::Bytes;
 
-use super::{MemoryStorage, RedisDataType, RedisObject, StorageResult as Result};
+use super::{random_sample, MemoryStorage, RedisDataType, RedisObject, StorageResult as Result};
 
 /// Set interface
 pub trait SetStorage {
@@ -11,6 +11,32 @@ pub trait SetStorage {
     fn scard(&self, key: &Bytes) -> Result<i64>;
     fn smembers(&self, key: &Bytes) -> Result<Vec<Bytes>>;
     fn sismember(&self, key: &Bytes, member: &Bytes) -> Result<bool>;
+    fn smismember(&self, key: &Bytes, members: &[Bytes]) -> Result<Vec<bool>>;
+    /// Get random members: up to `count` distinct members if positive, or `-count` members
+    /// that may repeat if negative
+    fn srandmember(&self, key: &Bytes, count: i64) -> Result<Vec<Bytes>>;
+    /// Remove and return up to `count` random members, deleting the set if empty
+    fn spop(&mut self, key: &Bytes, count: usize) -> Result<Vec<Bytes>>;
+    /// Perform the operation between the sets (missing keys are treated as empty sets)
+    fn setop(&self, op: SetOp, keys: &[Bytes]) -> Result<Vec<Bytes>>;
+    /// Perform the operation between the sets and store the result in the destination key
+    /// (deleting it if the result is empty). Returns the size of the result.
+    fn setop_store(&mut self, op: SetOp, dest: Bytes, keys: &[Bytes]) -> Result<i64>;
+    /// Count the members of the intersection of the sets, stopping once the limit is reached
+    /// (`0` for no limit)
+    fn sintercard(&self, keys: &[Bytes], limit: usize) -> Result<i64>;
+    /// Move the member from the source set to the destination set. Returns whether the member
+    /// was moved.
+    fn smove(&mut self, src: &Bytes, dest: Bytes, member: Bytes) -> Result<bool>;
+}
+
+/// Operation between sets for SINTER, SUNION and SDIFF
+#[derive(Debug, Clone, Copy)]
+pub enum SetOp {
+    Inter,
+    Union,
+    /// Members of the first set that aren't in any of the others
+    Diff,
 }
 
 impl SetStorage for MemoryStorage {
@@ -25,7 +51,7 @@ impl SetStorage for MemoryStorage {
     }
 
     fn srem(&mut self, key: &Bytes, members: Vec<Bytes>) -> Result<i64> {
-        let Some(set) = self.get_set_mut(&key)? else {
+        let Some(set) = self.get_set_mut(key)? else {
             return Ok(0);
         };
 
@@ -61,12 +87,119 @@ impl SetStorage for MemoryStorage {
             None => false,
         })
     }
+
+    fn smismember(&self, key: &Bytes, members: &[Bytes]) -> Result<Vec<bool>> {
+        Ok(match self.get_set(key)? {
+            Some(set) => members.iter().map(|m| set.contains(m)).collect(),
+            None => vec![false; members.len()],
+        })
+    }
+
+    fn srandmember(&self, key: &Bytes, count: i64) -> Result<Vec<Bytes>> {
+        Ok(match self.get_set(key)? {
+            Some(set) => random_sample(set.iter(), count)
+                .into_iter()
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        })
+    }
+
+    fn spop(&mut self, key: &Bytes, count: usize) -> Result<Vec<Bytes>> {
+        let Some(set) = self.get_set_mut(key)? else {
+            return Ok(Vec::new());
+        };
+        let count = count.try_into().unwrap_or(i64::MAX);
+        let members: Vec<_> = random_sample(set.iter(), count)
+            .into_iter()
+            .cloned()
+            .collect();
+        for member in &members {
+            set.remove(member);
+        }
+        if set.is_empty() {
+            self.data.remove(key);
+        }
+
+        Ok(members)
+    }
+
+    fn setop(&self, op: SetOp, keys: &[Bytes]) -> Result<Vec<Bytes>> {
+        let sets = self.get_sets(keys)?;
+        Ok(match op {
+            SetOp::Inter => intersect(sets).cloned().collect(),
+            SetOp::Union => {
+                let union: HashSet<_> = sets.into_iter().flatten().flatten().collect();
+                union.into_iter().cloned().collect()
+            }
+            SetOp::Diff => {
+                let mut sets = sets.into_iter();
+                let first = sets.next().flatten().into_iter().flatten();
+                let others: Vec<_> = sets.flatten().collect();
+                first
+                    .filter(|m| !others.iter().any(|set| set.contains(*m)))
+                    .cloned()
+                    .collect()
+            }
+        })
+    }
+
+    fn setop_store(&mut self, op: SetOp, dest: Bytes, keys: &[Bytes]) -> Result<i64> {
+        let result = self.setop(op, keys)?;
+        let len = result.len();
+        if result.is_empty() {
+            self.data.remove(&dest);
+        } else {
+            let object = RedisObject::new(RedisDataType::Set(result.into_iter().collect()));
+            self.data.insert(dest, object);
+        }
+
+        Ok(len.try_into().unwrap_or_default())
+    }
+
+    fn sintercard(&self, keys: &[Bytes], limit: usize) -> Result<i64> {
+        let limit = if limit == 0 { usize::MAX } else { limit };
+        let count = intersect(self.get_sets(keys)?).take(limit).count();
+        Ok(count.try_into().unwrap_or_default())
+    }
+
+    fn smove(&mut self, src: &Bytes, dest: Bytes, member: Bytes) -> Result<bool> {
+        // check the destination type before modifying the source
+        self.get_set(&dest)?;
+        let Some(set) = self.get_set_mut(src)? else {
+            return Ok(false);
+        };
+        if *src == dest {
+            return Ok(set.contains(&member));
+        }
+        if !set.remove(&member) {
+            return Ok(false);
+        }
+        if set.is_empty() {
+            self.data.remove(src);
+        }
+        self.get_set_entry(dest)?.insert(member);
+
+        Ok(true)
+    }
+}
+
+/// Iterate over the intersection of the sets, which is empty if any of them are missing
+fn intersect(sets: Vec<Option<&HashSet<Bytes>>>) -> impl Iterator<Item = &Bytes> {
+    let mut sets: Vec<_> = sets.into_iter().collect::<Option<_>>().unwrap_or_default();
+    // iterate over the smallest set
+    sets.sort_by_key(|set| set.len());
+    let smallest = (!sets.is_empty()).then(|| sets.remove(0));
+    smallest
+        .into_iter()
+        .flatten()
+        .filter(move |m| sets.iter().all(|set| set.contains(*m)))
 }
 
 const NOT_SET: Bytes = Bytes::from_static(b"Not a set");
 
 impl MemoryStorage {
-    fn get_set(&self, key: &Bytes) -> Result<Option<&HashSet<Bytes>>> {
+    pub(super) fn get_set(&self, key: &Bytes) -> Result<Option<&HashSet<Bytes>>> {
         let Some(data) = self.get(key) else {
             return Ok(None);
         };
@@ -76,6 +209,11 @@ impl MemoryStorage {
         Ok(Some(set))
     }
 
+    /// Get the sets at the keys, or `None` for missing keys
+    fn get_sets(&self, keys: &[Bytes]) -> Result<Vec<Option<&HashSet<Bytes>>>> {
+        keys.iter().map(|key| self.get_set(key)).collect()
+    }
+
     fn get_set_mut(&mut self, key: &Bytes) -> Result<Option<&mut HashSet<Bytes>>> {
         match self.get_mut(key) {
             Some(RedisDataType::Set(set)) => Ok(Some(set)),
@@ -1,23 +1,32 @@
 use std::{
     cmp::Ordering,
-    collections::{BTreeSet, HashMap},
+    collections::HashMap,
+    ops::{Bound, Range},
 };
 
 use bytes::Bytes;
 
-use super::{MemoryStorage, RedisDataType, RedisObject, StorageResult as Result};
+use super::{
+    random_sample, set::SetOp, string::normalize_range, MemoryStorage, RedisDataType, RedisObject,
+    SetCondition, StorageResult as Result,
+};
+
+mod skiplist;
+mod sorted_set_utils;
+use skiplist::SkipList;
+use sorted_set_utils::ScoredInput;
+
+/// Key of a sorted set and members popped from it, with their scores
+pub type KeyAndScoredMembers = (Bytes, Vec<(Bytes, f64)>);
 
 /// Sorted set storage:
 /// - HashMap of `member -> score`
-/// - BTreeSet of `{ member, score }` items ranked by score
+/// - SkipList of `{ member, score }` items ranked by score
 #[derive(Debug, Default, PartialEq)]
-pub struct SortedSet(
-    pub(super) HashMap<Bytes, f64>,
-    pub(super) BTreeSet<RankedItem>,
-);
+pub struct SortedSet(pub(super) HashMap<Bytes, f64>, pub(super) SkipList);
 
-/// Ranked item stored in the BTreeSet
-#[derive(Debug)]
+/// Ranked item stored in the SkipList
+#[derive(Debug, Clone)]
 pub struct RankedItem {
     pub(super) member: Bytes,
     pub(super) score: f64,
@@ -46,104 +55,300 @@ impl PartialEq for RankedItem {
     }
 }
 
+impl FromIterator<(Bytes, f64)> for SortedSet {
+    fn from_iter<I: IntoIterator<Item = (Bytes, f64)>>(iter: I) -> Self {
+        let hash: HashMap<_, _> = iter.into_iter().collect();
+        let ranked = hash
+            .iter()
+            .map(|(member, score)| RankedItem {
+                member: member.clone(),
+                score: *score,
+            })
+            .collect();
+        Self(hash, ranked)
+    }
+}
+
+impl SortedSet {
+    /// Iterate over the items with scores from `min` (inclusive) to `max` (exclusive)
+    pub(super) fn score_range(&self, min: f64, max: f64) -> impl Iterator<Item = &RankedItem> {
+        let ranks = match sorted_set_utils::score_bounds(Bound::Included(min), Bound::Excluded(max))
+        {
+            Some((start, end)) => self.1.rank_range(start.as_ref(), end.as_ref()),
+            None => 0..0,
+        };
+        self.1.range(ranks)
+    }
+
+    /// Insert the member or update its score. Returns the previous score.
+    pub(super) fn insert(&mut self, member: Bytes, score: f64) -> Option<f64> {
+        let SortedSet(hash, ranked) = self;
+        let old_score = hash.insert(member.clone(), score);
+        if let Some(old_score) = old_score {
+            ranked.remove(&RankedItem {
+                member: member.clone(),
+                score: old_score,
+            });
+        }
+        ranked.insert(RankedItem { member, score });
+        old_score
+    }
+}
+
+/// Options for combining sorted sets with ZUNION and ZINTER
+#[derive(Debug, Default)]
+pub struct ZSetOpOptions {
+    /// Multiplication factors for the scores of each input (the `WEIGHTS` option)
+    pub weights: Option<Vec<f64>>,
+    pub aggregate: ZAggregate,
+}
+
+/// How to combine the scores of a member in multiple inputs (the `AGGREGATE` option)
+#[derive(Debug, Default, Clone, Copy)]
+pub enum ZAggregate {
+    #[default]
+    Sum,
+    Min,
+    Max,
+}
+
+/// Conditions for adding members or updating scores with ZADD
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ZAddOptions {
+    /// Only add new members (`NX`) or only update existing members (`XX`)
+    pub condition: Option<SetCondition>,
+    /// Only update the score if the new score is greater (`GT`) or less (`LT`) than the current
+    /// score. New members are still added.
+    pub comparison: Option<ZAddComparison>,
+}
+
+/// Score comparison for ZADD (the `GT` and `LT` options)
+#[derive(Debug, Clone, Copy)]
+pub enum ZAddComparison {
+    GreaterThan,
+    LessThan,
+}
+
+impl ZAddOptions {
+    /// Whether the conditions are met for setting the new score, given the current score (if
+    /// the member exists)
+    fn is_met(&self, current: Option<f64>, score: f64) -> bool {
+        match (self.condition, current) {
+            (Some(SetCondition::NotExists), Some(_)) | (Some(SetCondition::Exists), None) => false,
+            (_, None) => true,
+            (_, Some(current)) => match self.comparison {
+                Some(ZAddComparison::GreaterThan) => score > current,
+                Some(ZAddComparison::LessThan) => score < current,
+                None => true,
+            },
+        }
+    }
+}
+
+/// A ZRANGE query
+#[derive(Debug)]
+pub struct ZRangeQuery {
+    pub by: ZRangeBy,
+    /// Order from the highest to the lowest score
+    pub rev: bool,
+    /// Offset and count (negative for all) of the results (the `LIMIT` option)
+    pub limit: Option<(i64, i64)>,
+}
+
+impl From<ZRangeBy> for ZRangeQuery {
+    fn from(by: ZRangeBy) -> Self {
+        Self {
+            by,
+            rev: false,
+            limit: None,
+        }
+    }
+}
+
+/// The type of range of a ZRANGE query
+#[derive(Debug)]
+pub enum ZRangeBy {
+    /// Inclusive start and stop ranks in the query order. Negative ranks count from the end.
+    Rank(i64, i64),
+    /// Minimum and maximum scores
+    Score(Bound<f64>, Bound<f64>),
+    /// Minimum and maximum members, where all members are expected to have the same score
+    Lex(Bound<Bytes>, Bound<Bytes>),
+}
+
 /// Sorted set interface
 pub trait SortedSetStorage {
-    fn zadd(&mut self, key: Bytes, members: Vec<(f64, Bytes)>) -> Result<i64>;
+    /// Add the members or update their scores, if the conditions are met. Returns the number
+    /// of members added and the number of scores updated.
+    fn zadd(
+        &mut self,
+        key: Bytes,
+        members: Vec<(f64, Bytes)>,
+        options: ZAddOptions,
+    ) -> Result<(i64, i64)>;
+    /// Increment the score of the member (adding it if needed), if the conditions are met.
+    /// Returns the new score, or `None` if the conditions weren't met.
+    fn zincrby(
+        &mut self,
+        key: Bytes,
+        incr: f64,
+        member: Bytes,
+        options: ZAddOptions,
+    ) -> Result<Option<f64>>;
     fn zrank(&self, key: &Bytes, member: Bytes) -> Result<Option<i64>>;
-    fn zrange(&self, key: &Bytes, start: i64, stop: i64) -> Result<Vec<Bytes>>;
+    /// Get the rank of the member, ordered from the highest to the lowest score
+    fn zrevrank(&self, key: &Bytes, member: Bytes) -> Result<Option<i64>>;
+    /// Get the members and scores in the range
+    fn zrange(&self, key: &Bytes, query: &ZRangeQuery) -> Result<Vec<(Bytes, f64)>>;
+    /// Store the members in the range at the destination key (deleting it if the range is
+    /// empty). Returns the number of members stored.
+    fn zrangestore(&mut self, dest: Bytes, src: &Bytes, query: &ZRangeQuery) -> Result<i64>;
     fn zcard(&self, key: &Bytes) -> Result<i64>;
     fn zscore(&self, key: &Bytes, member: &Bytes) -> Result<Option<f64>>;
     fn zrem(&mut self, key: &Bytes, member: Vec<Bytes>) -> Result<i64>;
+    fn zmscore(&self, key: &Bytes, members: &[Bytes]) -> Result<Vec<Option<f64>>>;
+    /// Count the members in the score or lexicographical range
+    fn zcount(&self, key: &Bytes, range: ZRangeBy) -> Result<i64>;
+    /// Remove the members in the range, deleting the sorted set if empty. Returns the number
+    /// of members removed.
+    fn zremrange(&mut self, key: &Bytes, range: ZRangeBy) -> Result<i64>;
+    /// Remove and return up to `count` members with the lowest scores, or the highest scores
+    /// if `max` is set. Deletes the sorted set if empty.
+    fn zpop(&mut self, key: &Bytes, max: bool, count: usize) -> Result<Vec<(Bytes, f64)>>;
+    /// Pop up to `count` members from the first of the keys that holds a sorted set. Returns the
+    /// key and the popped members, or `None` if none of the sorted sets exist.
+    fn zmpop(
+        &mut self,
+        keys: &[Bytes],
+        max: bool,
+        count: usize,
+    ) -> Result<Option<KeyAndScoredMembers>>;
+    /// Perform the operation between the sorted sets (or sets, where every member has a score of
+    /// 1). Returns the members and scores ordered by score.
+    fn zsetop(
+        &self,
+        op: SetOp,
+        keys: &[Bytes],
+        options: &ZSetOpOptions,
+    ) -> Result<Vec<(Bytes, f64)>>;
+    /// Perform the operation between the sorted sets (or sets) and store the result in the
+    /// destination key (deleting it if the result is empty). Returns the size of the result.
+    fn zsetop_store(
+        &mut self,
+        op: SetOp,
+        dest: Bytes,
+        keys: &[Bytes],
+        options: &ZSetOpOptions,
+    ) -> Result<i64>;
+    /// Count the members of the intersection of the sorted sets (or sets), up to the limit
+    /// (`0` for no limit)
+    fn zintercard(&self, keys: &[Bytes], limit: usize) -> Result<i64>;
+    /// Get random members and their scores: up to `count` distinct members if positive, or
+    /// `-count` members that may repeat if negative
+    fn zrandmember(&self, key: &Bytes, count: i64) -> Result<Vec<(Bytes, f64)>>;
 }
 
 impl SortedSetStorage for MemoryStorage {
-    fn zadd(&mut self, key: Bytes, members: Vec<(f64, Bytes)>) -> Result<i64> {
-        let SortedSet(hash, ranked) = self.get_sorted_set_entry(key)?;
-        let mut num_added = 0;
+    fn zadd(
+        &mut self,
+        key: Bytes,
+        members: Vec<(f64, Bytes)>,
+        options: ZAddOptions,
+    ) -> Result<(i64, i64)> {
+        if matches!(options.condition, Some(SetCondition::Exists))
+            && self.get_sorted_set(&key)?.is_none()
+        {
+            return Ok((0, 0));
+        }
+
+        let set = self.get_sorted_set_entry(key)?;
+        let (mut num_added, mut num_updated) = (0, 0);
         for (score, member) in members {
-            match hash.insert(member.clone(), score) {
-                None => {
-                    // New member
-                    ranked.insert(RankedItem { member, score });
-                    num_added += 1;
-                }
-                Some(old_score) => {
-                    // Update member in ranked tree
-                    let old_rank = ranked
-                        .take(&RankedItem {
-                            member,
-                            score: old_score,
-                        })
-                        .expect("existing member & score should be in ranked tree");
-                    ranked.insert(RankedItem {
-                        member: old_rank.member,
-                        score,
-                    });
-                }
+            let current = set.0.get(&member).copied();
+            if !options.is_met(current, score) {
+                continue;
             }
+            match set.insert(member, score) {
+                None => num_added += 1,
+                Some(old_score) if old_score != score => num_updated += 1,
+                Some(_) => {}
+            }
+        }
+
+        Ok((num_added, num_updated))
+    }
+
+    fn zincrby(
+        &mut self,
+        key: Bytes,
+        incr: f64,
+        member: Bytes,
+        options: ZAddOptions,
+    ) -> Result<Option<f64>> {
+        if matches!(options.condition, Some(SetCondition::Exists))
+            && self.get_sorted_set(&key)?.is_none()
+        {
+            return Ok(None);
+        }
+
+        let set = self.get_sorted_set_entry(key)?;
+        let current = set.0.get(&member).copied();
+        let score = current.unwrap_or_default() + incr;
+        if score.is_nan() {
+            return Err(NAN_SCORE);
+        }
+        if !options.is_met(current, score) {
+            return Ok(None);
         }
+        set.insert(member, score);
 
-        Ok(num_added)
+        Ok(Some(score))
     }
 
     fn zrank(&self, key: &Bytes, member: Bytes) -> Result<Option<i64>> {
         let Some(SortedSet(hash, ranked)) = self.get_sorted_set(key)? else {
             return Ok(None);
         };
-        // get member score, and lowest ranked member
-        let (Some(score), Some(lowest)) = (hash.get(&member), ranked.first()) else {
+        let Some(score) = hash.get(&member) else {
             return Ok(None);
         };
-        // calculate rank by iterating from lowest member
-        let rank = ranked
-            .range(
-                lowest..&RankedItem {
-                    member,
-                    score: *score,
-                },
-            )
-            .count();
+        let rank = ranked.rank(&RankedItem {
+            member,
+            score: *score,
+        });
 
         Ok(Some(rank.try_into().unwrap_or_default()))
     }
 
-    fn zrange(&self, key: &Bytes, start: i64, stop: i64) -> Result<Vec<Bytes>> {
+    fn zrevrank(&self, key: &Bytes, member: Bytes) -> Result<Option<i64>> {
+        let len = self.zcard(key)?;
+        Ok(self.zrank(key, member)?.map(|rank| len - 1 - rank))
+    }
+
+    fn zrange(&self, key: &Bytes, query: &ZRangeQuery) -> Result<Vec<(Bytes, f64)>> {
         let Some(SortedSet(_, ranked)) = self.get_sorted_set(key)? else {
             return Ok(Vec::new());
         };
-        let length = ranked.len();
 
-        let beg: usize = if start < 0 {
-            length
-                .checked_add_signed(start.try_into().unwrap_or_default())
-                .unwrap_or_default()
-        } else {
-            start as usize
-        };
-        if beg >= length {
-            return Ok(Vec::new());
-        }
+        let items = query_items(ranked, query);
+        Ok(items
+            .into_iter()
+            .map(|item| (item.member.clone(), item.score))
+            .collect())
+    }
 
-        let mut end: usize = if stop < 0 {
-            length
-                .checked_add_signed(stop.try_into().unwrap_or_default())
-                .unwrap_or_default()
+    fn zrangestore(&mut self, dest: Bytes, src: &Bytes, query: &ZRangeQuery) -> Result<i64> {
+        let items = self.zrange(src, query)?;
+        let len = items.len();
+        if items.is_empty() {
+            self.data.remove(&dest);
         } else {
-            stop as usize
-        };
-        if end >= length {
-            end = length - 1;
-        }
-        if beg > end {
-            return Ok(Vec::new());
+            let set = items.into_iter().collect();
+            let object = RedisObject::new(RedisDataType::SortedSet(set));
+            self.data.insert(dest, object);
         }
 
-        Ok(ranked
-            .iter()
-            .skip(beg)
-            .take(end - beg + 1)
-            .map(|item| item.member.clone())
-            .collect())
+        Ok(len.try_into().unwrap_or_default())
     }
 
     fn zcard(&self, key: &Bytes) -> Result<i64> {
@@ -161,7 +366,7 @@ impl SortedSetStorage for MemoryStorage {
     }
 
     fn zrem(&mut self, key: &Bytes, members: Vec<Bytes>) -> Result<i64> {
-        let Some(SortedSet(hash, ranked)) = self.get_sorted_set_mut(&key)? else {
+        let Some(SortedSet(hash, ranked)) = self.get_sorted_set_mut(key)? else {
             return Ok(0);
         };
 
@@ -178,9 +383,195 @@ impl SortedSetStorage for MemoryStorage {
 
         Ok(num_removed)
     }
+
+    fn zmscore(&self, key: &Bytes, members: &[Bytes]) -> Result<Vec<Option<f64>>> {
+        Ok(match self.get_sorted_set(key)? {
+            Some(SortedSet(hash, _)) => members.iter().map(|m| hash.get(m).copied()).collect(),
+            None => vec![None; members.len()],
+        })
+    }
+
+    fn zcount(&self, key: &Bytes, range: ZRangeBy) -> Result<i64> {
+        let Some(SortedSet(_, ranked)) = self.get_sorted_set(key)? else {
+            return Ok(0);
+        };
+        let query = ZRangeQuery::from(range);
+        let count = query_ranks(ranked, &query).len();
+        Ok(count.try_into().unwrap_or_default())
+    }
+
+    fn zremrange(&mut self, key: &Bytes, range: ZRangeBy) -> Result<i64> {
+        let Some(SortedSet(hash, ranked)) = self.get_sorted_set_mut(key)? else {
+            return Ok(0);
+        };
+        let query = ZRangeQuery::from(range);
+        let members: Vec<_> = query_items(ranked, &query)
+            .into_iter()
+            .map(|item| item.member.clone())
+            .collect();
+        for member in &members {
+            if let Some((member, score)) = hash.remove_entry(member) {
+                ranked.remove(&RankedItem { member, score });
+            }
+        }
+        if hash.is_empty() {
+            self.data.remove(key);
+        }
+
+        Ok(members.len().try_into().unwrap_or_default())
+    }
+
+    fn zpop(&mut self, key: &Bytes, max: bool, count: usize) -> Result<Vec<(Bytes, f64)>> {
+        let Some(SortedSet(hash, ranked)) = self.get_sorted_set_mut(key)? else {
+            return Ok(Vec::new());
+        };
+        let mut popped = Vec::new();
+        while popped.len() < count {
+            let item = match max {
+                true => ranked.pop_last(),
+                false => ranked.pop_first(),
+            };
+            let Some(RankedItem { member, score }) = item else {
+                break;
+            };
+            hash.remove(&member);
+            popped.push((member, score));
+        }
+        if hash.is_empty() {
+            self.data.remove(key);
+        }
+
+        Ok(popped)
+    }
+
+    fn zmpop(
+        &mut self,
+        keys: &[Bytes],
+        max: bool,
+        count: usize,
+    ) -> Result<Option<KeyAndScoredMembers>> {
+        for key in keys {
+            if self.get_sorted_set(key)?.is_some() {
+                let popped = self.zpop(key, max, count)?;
+                return Ok(Some((key.clone(), popped)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn zsetop(
+        &self,
+        op: SetOp,
+        keys: &[Bytes],
+        options: &ZSetOpOptions,
+    ) -> Result<Vec<(Bytes, f64)>> {
+        let SortedSet(_, ranked) = self.combine_sorted_sets(op, keys, options)?;
+        Ok(ranked
+            .iter()
+            .map(|item| (item.member.clone(), item.score))
+            .collect())
+    }
+
+    fn zsetop_store(
+        &mut self,
+        op: SetOp,
+        dest: Bytes,
+        keys: &[Bytes],
+        options: &ZSetOpOptions,
+    ) -> Result<i64> {
+        let result = self.combine_sorted_sets(op, keys, options)?;
+        let len = result.0.len();
+        if len == 0 {
+            self.data.remove(&dest);
+        } else {
+            let object = RedisObject::new(RedisDataType::SortedSet(result));
+            self.data.insert(dest, object);
+        }
+
+        Ok(len.try_into().unwrap_or_default())
+    }
+
+    fn zintercard(&self, keys: &[Bytes], limit: usize) -> Result<i64> {
+        let limit = if limit == 0 { usize::MAX } else { limit };
+        let count = sorted_set_utils::intersect_card(self.get_scored_inputs(keys)?, limit);
+        Ok(count.try_into().unwrap_or_default())
+    }
+
+    fn zrandmember(&self, key: &Bytes, count: i64) -> Result<Vec<(Bytes, f64)>> {
+        let Some(SortedSet(hash, _)) = self.get_sorted_set(key)? else {
+            return Ok(Vec::new());
+        };
+        let sample = random_sample(hash.iter(), count);
+        Ok(sample
+            .into_iter()
+            .map(|(member, score)| (member.clone(), *score))
+            .collect())
+    }
+}
+
+/// Get the items in the range of the query, in the query order
+fn query_items<'a>(ranked: &'a SkipList, query: &ZRangeQuery) -> Vec<&'a RankedItem> {
+    let items = ranked.range(query_ranks(ranked, query));
+    match query.rev {
+        true => items.rev().collect(),
+        false => items.collect(),
+    }
+}
+
+/// Get the ranks (in ascending order) of the items in the range of the query, after applying
+/// its `LIMIT`
+fn query_ranks(ranked: &SkipList, query: &ZRangeQuery) -> Range<usize> {
+    let len = ranked.len();
+    let ranks = match &query.by {
+        ZRangeBy::Rank(start, stop) => {
+            let Some((start, stop)) = normalize_range(*start, *stop, len) else {
+                return 0..0;
+            };
+            return match query.rev {
+                true => len - 1 - stop..len - start,
+                false => start..stop + 1,
+            };
+        }
+        ZRangeBy::Score(min, max) => {
+            let Some((start, end)) = sorted_set_utils::score_bounds(*min, *max) else {
+                return 0..0;
+            };
+            ranked.rank_range(start.as_ref(), end.as_ref())
+        }
+        ZRangeBy::Lex(min, max) => {
+            let Some(score) = ranked.first().map(|item| item.score) else {
+                return 0..0;
+            };
+            let Some((start, end)) = sorted_set_utils::lex_bounds(score, min.clone(), max.clone())
+            else {
+                return 0..0;
+            };
+            ranked.rank_range(start.as_ref(), end.as_ref())
+        }
+    };
+    limit_ranks(ranks, query.rev, query.limit)
+}
+
+/// Skip and take the `LIMIT` offset and count (negative for all) of the ranks, counting from
+/// the end if in reverse order
+fn limit_ranks(ranks: Range<usize>, rev: bool, limit: Option<(i64, i64)>) -> Range<usize> {
+    let Some((offset, count)) = limit else {
+        return ranks;
+    };
+    let offset = usize::try_from(offset)
+        .unwrap_or(usize::MAX)
+        .min(ranks.len());
+    let count = usize::try_from(count)
+        .unwrap_or(usize::MAX)
+        .min(ranks.len() - offset);
+    match rev {
+        true => ranks.end - offset - count..ranks.end - offset,
+        false => ranks.start + offset..ranks.start + offset + count,
+    }
 }
 
 const NOT_SORTED_SET: Bytes = Bytes::from_static(b"Not a sorted set");
+const NAN_SCORE: Bytes = Bytes::from_static(b"ERR resulting score is not a number (NaN)");
 const MALFORMED: Bytes = Bytes::from_static(b"Sorted set data is malformed");
 
 impl MemoryStorage {
@@ -198,6 +589,40 @@ impl MemoryStorage {
         Ok(Some(set))
     }
 
+    /// Get the inputs at the keys and combine them with the operation
+    fn combine_sorted_sets(
+        &self,
+        op: SetOp,
+        keys: &[Bytes],
+        options: &ZSetOpOptions,
+    ) -> Result<SortedSet> {
+        let inputs = self.get_scored_inputs(keys)?;
+        let weights = match &options.weights {
+            Some(weights) => weights.clone(),
+            None => vec![1.0; keys.len()],
+        };
+        Ok(sorted_set_utils::combine(
+            op,
+            inputs,
+            &weights,
+            options.aggregate,
+        ))
+    }
+
+    /// Get the sorted sets or sets to combine (`None` for missing keys)
+    fn get_scored_inputs(&self, keys: &[Bytes]) -> Result<Vec<Option<ScoredInput<'_>>>> {
+        keys.iter()
+            .map(|key| match self.get(key) {
+                Some(RedisDataType::SortedSet(SortedSet(hash, _))) => {
+                    Ok(Some(ScoredInput::SortedSet(hash)))
+                }
+                Some(RedisDataType::Set(set)) => Ok(Some(ScoredInput::Set(set))),
+                Some(_) => Err(NOT_SORTED_SET),
+                None => Ok(None),
+            })
+            .collect()
+    }
+
     pub(super) fn get_sorted_set_mut(&mut self, key: &Bytes) -> Result<Option<&mut SortedSet>> {
         match self.get_mut(key) {
             Some(RedisDataType::SortedSet(set)) => {
@@ -0,0 +1,478 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    ops::{Bound, Range},
+};
+
+use super::RankedItem;
+
+/// Maximum number of levels of the skiplist, enough for 4^32 items
+const MAX_LEVEL: usize = 32;
+
+/// Skiplist of ranked items, where each link also stores its span (the number of items it
+/// skips over). Summing the spans along a search path gives the rank of an item, so rank
+/// lookups and lookups by rank take O(log n), as in Redis's sorted sets.
+///
+/// Nodes are stored in an arena and linked by index, with freed slots reused by later inserts.
+pub struct SkipList {
+    /// Links from the head of the list, for all levels
+    head: Vec<Link>,
+    nodes: Vec<Option<Node>>,
+    free: Vec<usize>,
+    tail: Option<usize>,
+    /// Number of levels currently in use
+    level: usize,
+    len: usize,
+}
+
+#[derive(Debug)]
+struct Node {
+    item: RankedItem,
+    /// Previous node on the bottom level (`None` for the first node)
+    backward: Option<usize>,
+    links: Vec<Link>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Link {
+    forward: Option<usize>,
+    /// Number of items between the current node and the forward node, including the forward node
+    span: usize,
+}
+
+impl Default for SkipList {
+    fn default() -> Self {
+        Self {
+            head: vec![Link::default(); MAX_LEVEL],
+            nodes: Vec::new(),
+            free: Vec::new(),
+            tail: None,
+            level: 1,
+            len: 0,
+        }
+    }
+}
+
+impl SkipList {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Insert the item. Returns `false` if an equal item was already present.
+    pub fn insert(&mut self, item: RankedItem) -> bool {
+        let (mut update, mut rank) = self.search(&item);
+        let next = self.links(update[0])[0].forward;
+        if next.is_some_and(|next| self.node(next).item.cmp(&item) == Ordering::Equal) {
+            return false;
+        }
+
+        let level = random_level();
+        if level > self.level {
+            for i in self.level..level {
+                update[i] = None;
+                rank[i] = 0;
+                self.head[i].span = self.len;
+            }
+            self.level = level;
+        }
+
+        let idx = self.alloc(Node {
+            item,
+            backward: update[0],
+            links: vec![Link::default(); level],
+        });
+        for i in 0..level {
+            let prev = self.links(update[i])[i];
+            self.node_mut(idx).links[i] = Link {
+                forward: prev.forward,
+                span: prev.span - (rank[0] - rank[i]),
+            };
+            self.links_mut(update[i])[i] = Link {
+                forward: Some(idx),
+                span: rank[0] - rank[i] + 1,
+            };
+        }
+        // higher levels now skip over one more item
+        for (i, &prev) in update.iter().enumerate().take(self.level).skip(level) {
+            self.links_mut(prev)[i].span += 1;
+        }
+        match self.node(idx).links[0].forward {
+            Some(next) => self.node_mut(next).backward = Some(idx),
+            None => self.tail = Some(idx),
+        }
+        self.len += 1;
+
+        true
+    }
+
+    /// Remove the item, returning it if it was present
+    pub fn remove(&mut self, item: &RankedItem) -> Option<RankedItem> {
+        let (update, _) = self.search(item);
+        let idx = self.links(update[0])[0].forward?;
+        if self.node(idx).item.cmp(item) != Ordering::Equal {
+            return None;
+        }
+
+        let node = self.nodes[idx].take().expect("live node");
+        self.free.push(idx);
+        for (i, &prev) in update.iter().enumerate().take(self.level) {
+            let prev = &mut self.links_mut(prev)[i];
+            if prev.forward == Some(idx) {
+                prev.span += node.links[i].span;
+                prev.forward = node.links[i].forward;
+            }
+            prev.span -= 1;
+        }
+        match node.links[0].forward {
+            Some(next) => self.node_mut(next).backward = node.backward,
+            None => self.tail = node.backward,
+        }
+        while self.level > 1 && self.head[self.level - 1].forward.is_none() {
+            self.level -= 1;
+        }
+        self.len -= 1;
+
+        Some(node.item)
+    }
+
+    pub fn first(&self) -> Option<&RankedItem> {
+        self.head[0].forward.map(|idx| &self.node(idx).item)
+    }
+
+    pub fn pop_first(&mut self) -> Option<RankedItem> {
+        let first = self.head[0].forward?;
+        let item = self.node(first).item.clone();
+        self.remove(&item)
+    }
+
+    pub fn pop_last(&mut self) -> Option<RankedItem> {
+        let last = self.tail?;
+        let item = self.node(last).item.clone();
+        self.remove(&item)
+    }
+
+    /// Get the number of items ordered before the item, i.e. its rank if it's present
+    pub fn rank(&self, item: &RankedItem) -> usize {
+        self.count_until(item, false)
+    }
+
+    /// Get the range of ranks of the items within the bounds
+    pub fn rank_range(&self, start: Bound<&RankedItem>, end: Bound<&RankedItem>) -> Range<usize> {
+        let start = match start {
+            Bound::Included(item) => self.count_until(item, false),
+            Bound::Excluded(item) => self.count_until(item, true),
+            Bound::Unbounded => 0,
+        };
+        let end = match end {
+            Bound::Included(item) => self.count_until(item, true),
+            Bound::Excluded(item) => self.count_until(item, false),
+            Bound::Unbounded => self.len,
+        };
+        start..end.max(start)
+    }
+
+    /// Iterate over the items with the ranks in the range
+    pub fn range(&self, ranks: Range<usize>) -> Iter<'_> {
+        let end = ranks.end.min(self.len);
+        if ranks.start >= end {
+            return Iter {
+                list: self,
+                front: None,
+                back: None,
+                remaining: 0,
+            };
+        }
+        Iter {
+            list: self,
+            front: self.get_by_rank(ranks.start),
+            back: self.get_by_rank(end - 1),
+            remaining: end - ranks.start,
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            list: self,
+            front: self.head[0].forward,
+            back: self.tail,
+            remaining: self.len,
+        }
+    }
+
+    /// Find the last node before the item on each level (`None` for the head), and the rank
+    /// of each of those nodes (counting the head as rank 0)
+    fn search(&self, item: &RankedItem) -> ([Option<usize>; MAX_LEVEL], [usize; MAX_LEVEL]) {
+        let mut update = [None; MAX_LEVEL];
+        let mut rank = [0; MAX_LEVEL];
+        let mut current = None;
+        for i in (0..self.level).rev() {
+            rank[i] = rank.get(i + 1).copied().unwrap_or_default();
+            while let Link {
+                forward: Some(next),
+                span,
+            } = self.links(current)[i]
+            {
+                if self.node(next).item >= *item {
+                    break;
+                }
+                rank[i] += span;
+                current = Some(next);
+            }
+            update[i] = current;
+        }
+        (update, rank)
+    }
+
+    /// Count the items ordered before the item (or equal to it, if inclusive)
+    fn count_until(&self, item: &RankedItem, inclusive: bool) -> usize {
+        let mut count = 0;
+        let mut current = None;
+        for i in (0..self.level).rev() {
+            while let Link {
+                forward: Some(next),
+                span,
+            } = self.links(current)[i]
+            {
+                match self.node(next).item.cmp(item) {
+                    Ordering::Less => {}
+                    Ordering::Equal if inclusive => {}
+                    _ => break,
+                }
+                count += span;
+                current = Some(next);
+            }
+        }
+        count
+    }
+
+    /// Get the node with the (0-based) rank
+    fn get_by_rank(&self, rank: usize) -> Option<usize> {
+        let target = rank + 1;
+        let mut traversed = 0;
+        let mut current = None;
+        for i in (0..self.level).rev() {
+            while let Link {
+                forward: Some(next),
+                span,
+            } = self.links(current)[i]
+            {
+                if traversed + span > target {
+                    break;
+                }
+                traversed += span;
+                current = Some(next);
+            }
+            if traversed == target {
+                return current;
+            }
+        }
+        None
+    }
+
+    fn alloc(&mut self, node: Node) -> usize {
+        match self.free.pop() {
+            Some(idx) => {
+                self.nodes[idx] = Some(node);
+                idx
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn node(&self, idx: usize) -> &Node {
+        self.nodes[idx].as_ref().expect("live node")
+    }
+
+    fn node_mut(&mut self, idx: usize) -> &mut Node {
+        self.nodes[idx].as_mut().expect("live node")
+    }
+
+    /// Get the links of the node, or of the head if `None`
+    fn links(&self, idx: Option<usize>) -> &[Link] {
+        match idx {
+            Some(idx) => &self.node(idx).links,
+            None => &self.head,
+        }
+    }
+
+    fn links_mut(&mut self, idx: Option<usize>) -> &mut [Link] {
+        match idx {
+            Some(idx) => &mut self.node_mut(idx).links,
+            None => &mut self.head,
+        }
+    }
+}
+
+/// Pick a random level for a new node, where each level is 4 times less likely than the last
+fn random_level() -> usize {
+    let mut level = 1;
+    while level < MAX_LEVEL && rand::random::<u32>() % 4 == 0 {
+        level += 1;
+    }
+    level
+}
+
+/// Iterator over a range of the skiplist, in ascending order
+pub struct Iter<'a> {
+    list: &'a SkipList,
+    front: Option<usize>,
+    back: Option<usize>,
+    remaining: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a RankedItem;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = self.list.node(self.front?);
+        self.front = node.links[0].forward;
+        self.remaining -= 1;
+        Some(&node.item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = self.list.node(self.back?);
+        self.back = node.backward;
+        self.remaining -= 1;
+        Some(&node.item)
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+impl FromIterator<RankedItem> for SkipList {
+    fn from_iter<I: IntoIterator<Item = RankedItem>>(iter: I) -> Self {
+        let mut list = Self::default();
+        for item in iter {
+            list.insert(item);
+        }
+        list
+    }
+}
+
+impl PartialEq for SkipList {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len
+            && self
+                .iter()
+                .zip(other.iter())
+                .all(|(a, b)| a.cmp(b) == Ordering::Equal)
+    }
+}
+
+impl fmt::Debug for SkipList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use bytes::Bytes;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    fn item(score: f64, member: &str) -> RankedItem {
+        RankedItem {
+            member: Bytes::copy_from_slice(member.as_bytes()),
+            score,
+        }
+    }
+
+    fn members<'a>(items: impl Iterator<Item = &'a RankedItem>) -> Vec<&'a [u8]> {
+        items.map(|item| item.member.as_ref()).collect()
+    }
+
+    #[test]
+    fn ranks_and_ranges() {
+        let mut list: SkipList = [
+            item(2.0, "b"),
+            item(1.0, "a"),
+            item(2.0, "c"),
+            item(3.0, "d"),
+        ]
+        .into_iter()
+        .collect();
+        assert!(!list.insert(item(2.0, "b")));
+        assert_eq!(list.len(), 4);
+
+        assert_eq!(list.rank(&item(2.0, "c")), 2);
+        assert_eq!(list.rank(&item(0.0, "z")), 0);
+        assert_eq!(list.rank(&item(9.0, "z")), 4);
+        assert_eq!(members(list.range(1..3)), [b"b", b"c"]);
+        assert_eq!(members(list.range(2..10).rev()), [b"d", b"c"]);
+        assert_eq!(list.range(4..6).count(), 0);
+        assert_eq!(
+            list.rank_range(
+                Bound::Excluded(&item(1.0, "a")),
+                Bound::Included(&item(2.0, "c"))
+            ),
+            1..3
+        );
+        assert_eq!(
+            list.rank_range(Bound::Included(&item(3.0, "")), Bound::Unbounded),
+            3..4
+        );
+
+        assert_eq!(list.remove(&item(1.0, "b")), None);
+        assert_eq!(
+            list.remove(&item(2.0, "b")).map(|i| i.member),
+            Some("b".into())
+        );
+        assert_eq!(list.pop_last().map(|i| i.member), Some("d".into()));
+        assert_eq!(list.pop_first().map(|i| i.member), Some("a".into()));
+        assert_eq!(members(list.iter()), [b"c"]);
+        assert_eq!(list.first().map(|i| i.score), Some(2.0));
+    }
+
+    #[test]
+    fn matches_btree() {
+        let mut rng = StdRng::seed_from_u64(21);
+        let mut list = SkipList::default();
+        let mut tree = BTreeSet::new();
+        for _ in 0..5000 {
+            let score = rng.random_range(0..50) as f64;
+            let member = rng.random_range(0..200).to_string();
+            if rng.random_bool(0.6) {
+                assert_eq!(
+                    list.insert(item(score, &member)),
+                    tree.insert(item(score, &member))
+                );
+            } else {
+                assert_eq!(
+                    list.remove(&item(score, &member)).is_some(),
+                    tree.remove(&item(score, &member))
+                );
+            }
+        }
+
+        assert_eq!(list.len(), tree.len());
+        assert!(list.iter().eq(tree.iter()));
+        assert!(list.iter().rev().eq(tree.iter().rev()));
+        for (rank, item) in tree.iter().enumerate() {
+            assert_eq!(list.rank(item), rank);
+            assert_eq!(list.range(rank..rank + 1).next(), Some(item));
+        }
+        let (start, end) = (item(10.0, ""), item(20.0, ""));
+        let ranks = list.rank_range(Bound::Included(&start), Bound::Excluded(&end));
+        assert!(list.range(ranks).eq(tree.range(start..end)));
+    }
+}
@@ -0,0 +1,338 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Bound,
+};
+
+use bytes::Bytes;
+
+use super::{RankedItem, SetOp, SortedSet, ZAggregate};
+
+/// Bounds of a range query on the ranked items
+pub type RankedBounds = (Bound<RankedItem>, Bound<RankedItem>);
+
+/// Convert the score bounds into bounds of the ranked items. Returns `None` if the range is
+/// empty.
+pub fn score_bounds(min: Bound<f64>, max: Bound<f64>) -> Option<RankedBounds> {
+    // the empty member sorts before every other member with the same score
+    let item = |score| RankedItem {
+        member: Bytes::new(),
+        score,
+    };
+    let start = match min {
+        Bound::Included(score) => Bound::Included(item(score)),
+        Bound::Excluded(score) if score == f64::INFINITY => return None,
+        Bound::Excluded(score) => Bound::Included(item(next_score(score))),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+    let end = match max {
+        Bound::Included(score) if score == f64::INFINITY => Bound::Unbounded,
+        Bound::Included(score) => Bound::Excluded(item(next_score(score))),
+        Bound::Excluded(score) => Bound::Excluded(item(score)),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+    is_valid(&start, &end).then_some((start, end))
+}
+
+/// Get the next representable score above the score. Same as `f64::next_up`, which needs a
+/// newer Rust version than the one the server is built with.
+fn next_score(score: f64) -> f64 {
+    if score.is_nan() || score == f64::INFINITY {
+        return score;
+    }
+    let bits = match score {
+        // the smallest positive subnormal, for both zeros
+        0.0 => 1,
+        score if score > 0.0 => score.to_bits() + 1,
+        score => score.to_bits() - 1,
+    };
+    f64::from_bits(bits)
+}
+
+/// Convert the lexicographical bounds into bounds of the ranked items, where all members are
+/// expected to have the given score. Returns `None` if the range is empty.
+pub fn lex_bounds(score: f64, min: Bound<Bytes>, max: Bound<Bytes>) -> Option<RankedBounds> {
+    let item = |member| RankedItem { member, score };
+    let start = min.map(item);
+    let end = max.map(item);
+    is_valid(&start, &end).then_some((start, end))
+}
+
+/// Whether the range of ranked items is non-empty
+fn is_valid(start: &Bound<RankedItem>, end: &Bound<RankedItem>) -> bool {
+    match (start, end) {
+        (Bound::Included(start), Bound::Included(end)) => start <= end,
+        (
+            Bound::Included(start) | Bound::Excluded(start),
+            Bound::Included(end) | Bound::Excluded(end),
+        ) => start < end,
+        _ => true,
+    }
+}
+
+/// An input of ZUNION, ZINTER, etc: a sorted set, or a set where every member has a score of 1
+pub enum ScoredInput<'a> {
+    SortedSet(&'a HashMap<Bytes, f64>),
+    Set(&'a HashSet<Bytes>),
+}
+
+impl ScoredInput<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Self::SortedSet(hash) => hash.len(),
+            Self::Set(set) => set.len(),
+        }
+    }
+
+    fn score(&self, member: &Bytes) -> Option<f64> {
+        match self {
+            Self::SortedSet(hash) => hash.get(member).copied(),
+            Self::Set(set) => set.contains(member).then_some(1.0),
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (&Bytes, f64)> + '_> {
+        match self {
+            Self::SortedSet(hash) => Box::new(hash.iter().map(|(member, score)| (member, *score))),
+            Self::Set(set) => Box::new(set.iter().map(|member| (member, 1.0))),
+        }
+    }
+}
+
+impl ZAggregate {
+    /// Combine the two scores. A sum of opposite infinities is `0`.
+    pub fn apply(self, a: f64, b: f64) -> f64 {
+        match self {
+            Self::Sum => zero_if_nan(a + b),
+            Self::Min => a.min(b),
+            Self::Max => a.max(b),
+        }
+    }
+}
+
+fn zero_if_nan(score: f64) -> f64 {
+    if score.is_nan() {
+        0.0
+    } else {
+        score
+    }
+}
+
+/// Count the members in the intersection of the inputs (`None` for missing keys), stopping
+/// once the limit is reached
+pub fn intersect_card(inputs: Vec<Option<ScoredInput>>, limit: usize) -> usize {
+    let Some(mut inputs) = inputs.into_iter().collect::<Option<Vec<_>>>() else {
+        return 0;
+    };
+    // iterate over the smallest input
+    inputs.sort_by_key(ScoredInput::len);
+    let Some((smallest, others)) = inputs.split_first() else {
+        return 0;
+    };
+    smallest
+        .iter()
+        .filter(|(member, _)| others.iter().all(|input| input.score(member).is_some()))
+        .take(limit)
+        .count()
+}
+
+/// Combine the inputs (`None` for missing keys) with the operation, multiplying the scores
+/// of each input by its weight. The difference keeps the scores of the first input.
+pub fn combine(
+    op: SetOp,
+    inputs: Vec<Option<ScoredInput>>,
+    weights: &[f64],
+    aggregate: ZAggregate,
+) -> SortedSet {
+    let weighted = |score: f64, weight: f64| zero_if_nan(score * weight);
+    let mut inputs: Vec<_> = inputs.into_iter().zip(weights.iter().copied()).collect();
+    match op {
+        SetOp::Union => {
+            let mut scores: HashMap<Bytes, f64> = HashMap::new();
+            for (input, weight) in inputs.iter().filter_map(|(i, w)| Some((i.as_ref()?, *w))) {
+                for (member, score) in input.iter() {
+                    let score = weighted(score, weight);
+                    scores
+                        .entry(member.clone())
+                        .and_modify(|current| *current = aggregate.apply(*current, score))
+                        .or_insert(score);
+                }
+            }
+            scores.into_iter().collect()
+        }
+        SetOp::Inter => {
+            if inputs.iter().any(|(input, _)| input.is_none()) {
+                return SortedSet::default();
+            }
+            // iterate over the smallest input
+            inputs.sort_by_key(|(input, _)| input.as_ref().map(ScoredInput::len));
+            let Some(((Some(smallest), first_weight), others)) = inputs.split_first() else {
+                return SortedSet::default();
+            };
+            smallest
+                .iter()
+                .filter_map(|(member, score)| {
+                    others
+                        .iter()
+                        .try_fold(weighted(score, *first_weight), |total, (input, weight)| {
+                            let score = input.as_ref()?.score(member)?;
+                            Some(aggregate.apply(total, weighted(score, *weight)))
+                        })
+                        .map(|total| (member.clone(), total))
+                })
+                .collect()
+        }
+        SetOp::Diff => {
+            let Some(((Some(first), _), others)) = inputs.split_first() else {
+                return SortedSet::default();
+            };
+            first
+                .iter()
+                .filter(|(member, _)| {
+                    !others
+                        .iter()
+                        .any(|(input, _)| input.as_ref().is_some_and(|i| i.score(member).is_some()))
+                })
+                .map(|(member, score)| (member.clone(), score))
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    fn ranked(items: &[(f64, &'static str)]) -> BTreeSet<RankedItem> {
+        items
+            .iter()
+            .map(|(score, member)| RankedItem {
+                member: Bytes::from_static(member.as_bytes()),
+                score: *score,
+            })
+            .collect()
+    }
+
+    fn members(set: &BTreeSet<RankedItem>, bounds: Option<RankedBounds>) -> Vec<&str> {
+        let Some(bounds) = bounds else {
+            return Vec::new();
+        };
+        set.range(bounds)
+            .map(|item| std::str::from_utf8(&item.member).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn next_scores() {
+        assert_eq!(next_score(1.0), 1.0 + f64::EPSILON);
+        assert_eq!(next_score(-1.0), -1.0 + f64::EPSILON / 2.0);
+        assert_eq!(next_score(0.0), f64::from_bits(1));
+        assert_eq!(next_score(-0.0), f64::from_bits(1));
+        assert_eq!(next_score(-f64::from_bits(1)), -0.0);
+        assert_eq!(next_score(f64::NEG_INFINITY), f64::MIN);
+        assert_eq!(next_score(f64::MAX), f64::INFINITY);
+        assert_eq!(next_score(f64::INFINITY), f64::INFINITY);
+    }
+
+    #[test]
+    fn score_ranges() {
+        let set = ranked(&[(1.0, "a"), (2.0, "b"), (2.0, "c"), (3.0, "d")]);
+        let range = |min, max| members(&set, score_bounds(min, max));
+
+        assert_eq!(
+            range(Bound::Included(2.0), Bound::Included(3.0)),
+            ["b", "c", "d"]
+        );
+        assert_eq!(
+            range(Bound::Excluded(1.0), Bound::Excluded(3.0)),
+            ["b", "c"]
+        );
+        assert_eq!(
+            range(Bound::Included(f64::NEG_INFINITY), Bound::Excluded(2.0)),
+            ["a"]
+        );
+        assert_eq!(range(Bound::Excluded(2.0), Bound::Unbounded), ["d"]);
+        assert!(range(Bound::Excluded(2.0), Bound::Excluded(2.0)).is_empty());
+        assert!(range(Bound::Included(3.0), Bound::Included(1.0)).is_empty());
+
+        let set = ranked(&[(1.0, "a"), (f64::INFINITY, "b")]);
+        let range = |min, max| members(&set, score_bounds(min, max));
+        assert_eq!(
+            range(Bound::Included(1.0), Bound::Included(f64::INFINITY)),
+            ["a", "b"]
+        );
+        assert!(range(Bound::Excluded(f64::INFINITY), Bound::Unbounded).is_empty());
+    }
+
+    #[test]
+    fn combine_inputs() {
+        let b = |s: &'static str| Bytes::from_static(s.as_bytes());
+        let day1 = HashMap::from([(b("a"), 1.0), (b("b"), 2.0), (b("c"), f64::INFINITY)]);
+        let day2 = HashMap::from([(b("b"), 3.0), (b("c"), f64::NEG_INFINITY)]);
+        let tags = HashSet::from([b("a"), b("b")]);
+        let inputs = || {
+            vec![
+                Some(ScoredInput::SortedSet(&day1)),
+                Some(ScoredInput::SortedSet(&day2)),
+                Some(ScoredInput::Set(&tags)),
+            ]
+        };
+        let scores = |set: SortedSet| {
+            let mut scores: Vec<_> = set.0.into_iter().collect();
+            scores.sort_by(|a, b| a.0.cmp(&b.0));
+            scores
+        };
+
+        let union = combine(SetOp::Union, inputs(), &[1.0, 2.0, 1.0], ZAggregate::Sum);
+        assert_eq!(scores(union), [(b("a"), 2.0), (b("b"), 9.0), (b("c"), 0.0)]);
+        let inter = combine(SetOp::Inter, inputs(), &[1.0; 3], ZAggregate::Max);
+        assert_eq!(scores(inter), [(b("b"), 3.0)]);
+        assert_eq!(intersect_card(inputs(), usize::MAX), 1);
+        assert_eq!(
+            intersect_card(inputs().into_iter().take(2).collect(), usize::MAX),
+            2
+        );
+        assert_eq!(intersect_card(inputs().into_iter().take(2).collect(), 1), 1);
+        assert_eq!(
+            intersect_card(vec![inputs().remove(0), None], usize::MAX),
+            0
+        );
+        let diff = combine(SetOp::Diff, inputs(), &[1.0; 3], ZAggregate::Sum);
+        assert!(diff.0.is_empty());
+
+        let mut missing = inputs();
+        missing.push(None);
+        let inter = combine(SetOp::Inter, missing, &[1.0; 4], ZAggregate::Sum);
+        assert!(inter.0.is_empty());
+        let diff = combine(
+            SetOp::Diff,
+            vec![Some(ScoredInput::SortedSet(&day1)), None],
+            &[1.0; 2],
+            ZAggregate::Min,
+        );
+        assert_eq!(
+            scores(diff),
+            [(b("a"), 1.0), (b("b"), 2.0), (b("c"), f64::INFINITY)]
+        );
+    }
+
+    #[test]
+    fn lex_ranges() {
+        let set = ranked(&[(0.0, "a"), (0.0, "b"), (0.0, "c"), (0.0, "d")]);
+        let range = |min, max| members(&set, lex_bounds(0.0, min, max));
+        let b = |s: &'static str| Bytes::from_static(s.as_bytes());
+
+        assert_eq!(
+            range(Bound::Included(b("b")), Bound::Excluded(b("d"))),
+            ["b", "c"]
+        );
+        assert_eq!(range(Bound::Unbounded, Bound::Included(b("a"))), ["a"]);
+        assert_eq!(
+            range(Bound::Excluded(b("bb")), Bound::Unbounded),
+            ["c", "d"]
+        );
+        assert!(range(Bound::Excluded(b("b")), Bound::Excluded(b("b"))).is_empty());
+        assert!(range(Bound::Included(b("d")), Bound::Included(b("a"))).is_empty());
+    }
+}
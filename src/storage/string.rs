@@ -0,0 +1,116 @@
+use bytes::{Bytes, BytesMut};
+
+use super::{MemoryStorage, RedisDataType, RedisObject, StorageResult as Result};
+
+mod string_utils;
+pub(super) use string_utils::normalize_range;
+pub use string_utils::Lcs;
+
+/// String interface
+pub trait StringStorage {
+    /// Append to the string, creating it if needed. Returns the new length.
+    fn append(&mut self, key: Bytes, val: Bytes) -> Result<i64>;
+    fn strlen(&self, key: &Bytes) -> Result<i64>;
+    /// Get the substring between the inclusive start and end indexes. Negative indexes
+    /// count from the end of the string.
+    fn getrange(&self, key: &Bytes, start: i64, end: i64) -> Result<Bytes>;
+    /// Overwrite part of the string at the offset, padding with zero bytes if needed.
+    /// Returns the new length.
+    fn setrange(&mut self, key: Bytes, offset: usize, val: Bytes) -> Result<i64>;
+    /// Find the longest common subsequence of two strings. Missing keys are treated as empty strings.
+    /// Fails if the strings are too long for the table used to compare them.
+    fn lcs(&self, key1: &Bytes, key2: &Bytes) -> Result<Lcs>;
+}
+
+impl StringStorage for MemoryStorage {
+    fn append(&mut self, key: Bytes, val: Bytes) -> Result<i64> {
+        let string = self.get_string_entry(key)?;
+        let len = string.len() + val.len();
+        if len > MAX_STRING_LEN {
+            return Err(MAX_STRING_LEN_EXCEEDED);
+        }
+        let mut buf = BytesMut::from(std::mem::take(string));
+        buf.extend_from_slice(&val);
+        *string = buf.freeze();
+
+        Ok(len.try_into().unwrap_or_default())
+    }
+
+    fn strlen(&self, key: &Bytes) -> Result<i64> {
+        Ok(match self.get_string(key)? {
+            Some(string) => string.len().try_into().unwrap_or_default(),
+            None => 0,
+        })
+    }
+
+    fn getrange(&self, key: &Bytes, start: i64, end: i64) -> Result<Bytes> {
+        let Some(string) = self.get_string(key)? else {
+            return Ok(Bytes::new());
+        };
+        Ok(match normalize_range(start, end, string.len()) {
+            Some((start, end)) => string.slice(start..=end),
+            None => Bytes::new(),
+        })
+    }
+
+    fn setrange(&mut self, key: Bytes, offset: usize, val: Bytes) -> Result<i64> {
+        if val.is_empty() {
+            let len = self.get_string(&key)?.map_or(0, |string| string.len());
+            return Ok(len.try_into().unwrap_or_default());
+        }
+        let Some(end) = offset
+            .checked_add(val.len())
+            .filter(|end| *end <= MAX_STRING_LEN)
+        else {
+            return Err(MAX_STRING_LEN_EXCEEDED);
+        };
+
+        let string = self.get_string_entry(key)?;
+        let mut buf = BytesMut::from(std::mem::take(string));
+        if buf.len() < end {
+            buf.resize(end, 0);
+        }
+        buf[offset..end].copy_from_slice(&val);
+        *string = buf.freeze();
+
+        Ok(string.len().try_into().unwrap_or_default())
+    }
+
+    fn lcs(&self, key1: &Bytes, key2: &Bytes) -> Result<Lcs> {
+        let a = self.get_string(key1)?.unwrap_or_default();
+        let b = self.get_string(key2)?.unwrap_or_default();
+        string_utils::lcs(&a, &b, MAX_STRING_LEN).ok_or(LCS_MEMORY_EXCEEDED)
+    }
+}
+
+/// Maximum string length (512MB)
+const MAX_STRING_LEN: usize = 512 * 1024 * 1024;
+
+pub(super) const NOT_STRING: Bytes = Bytes::from_static(b"Not a string");
+const MAX_STRING_LEN_EXCEEDED: Bytes =
+    Bytes::from_static(b"ERR string exceeds maximum allowed size (proto-max-bulk-len)");
+const LCS_MEMORY_EXCEEDED: Bytes = Bytes::from_static(
+    b"ERR Insufficient memory, transient memory for LCS exceeds proto-max-bulk-len",
+);
+
+impl MemoryStorage {
+    pub(super) fn get_string(&self, key: &Bytes) -> Result<Option<Bytes>> {
+        match self.get(key) {
+            Some(data) => Ok(Some(data.to_string_bytes().ok_or(NOT_STRING)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub(super) fn get_string_entry(&mut self, key: Bytes) -> Result<&mut Bytes> {
+        let entry = self.get_entry_with_default(key, || {
+            RedisObject::new(RedisDataType::String(Bytes::new()))
+        });
+        if let RedisDataType::Integer(int) = entry.data {
+            entry.data = RedisDataType::String(Bytes::from(int.to_string()));
+        }
+        let RedisDataType::String(ref mut string) = entry.data else {
+            return Err(NOT_STRING);
+        };
+        Ok(string)
+    }
+}
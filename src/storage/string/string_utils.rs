@@ -0,0 +1,144 @@
+//! String algorithm utilities
+
+/// Convert the inclusive start and end indexes (negative indexes count from the end) into
+/// valid indexes for the given length, using Redis's clamping rules. Returns `None` if the
+/// range is empty.
+pub fn normalize_range(start: i64, end: i64, len: usize) -> Option<(usize, usize)> {
+    if start < 0 && end < 0 && start > end {
+        return None;
+    }
+    let len = len as i64;
+    let normalize = |idx: i64| {
+        if idx < 0 {
+            len.saturating_add(idx)
+        } else {
+            idx
+        }
+    };
+    let start = normalize(start).max(0);
+    let end = normalize(end).min(len - 1);
+    if start > end || len == 0 {
+        return None;
+    }
+    Some((start as usize, end as usize))
+}
+
+/// Longest common subsequence of two strings
+#[derive(Debug, PartialEq)]
+pub struct Lcs {
+    /// The common subsequence
+    pub sequence: Vec<u8>,
+    /// The matching ranges, from the end of the strings to the start
+    pub matches: Vec<LcsMatch>,
+}
+
+/// A contiguous range in the LCS (inclusive indexes)
+#[derive(Debug, PartialEq)]
+pub struct LcsMatch {
+    pub a: (usize, usize),
+    pub b: (usize, usize),
+    pub len: usize,
+}
+
+/// Find the longest common subsequence using the dynamic programming table, and walk it
+/// back to collect the matching ranges (same ordering as Redis). Returns `None` if the table
+/// would take more than `max_table_bytes` of memory.
+pub fn lcs(a: &[u8], b: &[u8], max_table_bytes: usize) -> Option<Lcs> {
+    let width = b.len() + 1;
+    let table_len = (a.len() + 1).checked_mul(width)?;
+    if table_len.checked_mul(size_of::<u32>())? > max_table_bytes {
+        return None;
+    }
+    let mut table = vec![0u32; table_len];
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            table[i * width + j] = if a[i - 1] == b[j - 1] {
+                table[(i - 1) * width + j - 1] + 1
+            } else {
+                table[(i - 1) * width + j].max(table[i * width + j - 1])
+            };
+        }
+    }
+
+    let mut sequence = Vec::with_capacity(table[a.len() * width + b.len()] as usize);
+    let mut matches = Vec::new();
+    let mut current: Option<LcsMatch> = None;
+    let (mut i, mut j) = (a.len(), b.len());
+    while i > 0 && j > 0 {
+        if a[i - 1] == b[j - 1] {
+            sequence.push(a[i - 1]);
+            match current.as_mut() {
+                // extend the range backward since it's contiguous
+                Some(range) => {
+                    range.a.0 -= 1;
+                    range.b.0 -= 1;
+                    range.len += 1;
+                }
+                None => {
+                    current = Some(LcsMatch {
+                        a: (i - 1, i - 1),
+                        b: (j - 1, j - 1),
+                        len: 1,
+                    })
+                }
+            }
+            i -= 1;
+            j -= 1;
+        } else {
+            if table[(i - 1) * width + j] > table[i * width + j - 1] {
+                i -= 1;
+            } else {
+                j -= 1;
+            }
+            matches.extend(current.take());
+        }
+    }
+    matches.extend(current);
+    sequence.reverse();
+
+    Some(Lcs { sequence, matches })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_ranges() {
+        assert_eq!(normalize_range(0, -1, 6), Some((0, 5)));
+        assert_eq!(normalize_range(-3, 100, 6), Some((3, 5)));
+        assert_eq!(normalize_range(-100, 2, 6), Some((0, 2)));
+        assert_eq!(normalize_range(4, 2, 6), None);
+        assert_eq!(normalize_range(-1, -3, 6), None);
+        assert_eq!(normalize_range(0, -1, 0), None);
+    }
+
+    #[test]
+    fn longest_common_subsequence() {
+        let result = lcs(b"ohmytext", b"mynewtext", usize::MAX).unwrap();
+        assert_eq!(result.sequence, b"mytext");
+        assert_eq!(
+            result.matches,
+            vec![
+                LcsMatch {
+                    a: (4, 7),
+                    b: (5, 8),
+                    len: 4
+                },
+                LcsMatch {
+                    a: (2, 3),
+                    b: (0, 1),
+                    len: 2
+                }
+            ]
+        );
+
+        let result = lcs(b"abc", b"xyz", usize::MAX).unwrap();
+        assert!(result.sequence.is_empty());
+        assert!(result.matches.is_empty());
+
+        // a 4x4 table of u32
+        assert!(lcs(b"abc", b"xyz", 64).is_some());
+        assert!(lcs(b"abc", b"xyz", 63).is_none());
+    }
+}
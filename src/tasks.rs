@@ -6,6 +6,7 @@ use tokio::{
 };
 
 mod bpop;
+mod bzpop;
 mod cleanup;
 mod counters;
 mod notifiers;
@@ -26,6 +27,7 @@ pub fn spawn_server_tasks(
 ) -> (JoinSet<()>, Arc<Queues>, Arc<Notifiers>) {
     // Setup channels
     let (bpop_tx, bpop_rx) = mpsc::unbounded_channel();
+    let (bzpop_tx, bzpop_rx) = mpsc::unbounded_channel();
     let (xread_tx, xread_rx) = mpsc::unbounded_channel();
     let (pubsub_tx, pubsub_rx) = mpsc::unbounded_channel();
 
@@ -34,6 +36,7 @@ pub fn spawn_server_tasks(
     let counters: Arc<ChangeCounter> = Arc::default();
     let notifiers: Arc<Notifiers> = Arc::new(Notifiers {
         bpop: bpop_tx,
+        bzpop: bzpop_tx,
         xread: xread_tx,
         pubsub: pubsub_tx,
         counters: Arc::clone(&counters),
@@ -47,6 +50,12 @@ pub fn spawn_server_tasks(
         bpop_rx,
         shutdown_sig.clone(),
     ));
+    all_tasks.spawn(bzpop::bzpop_task(
+        Arc::clone(storage),
+        Arc::clone(&queues),
+        bzpop_rx,
+        shutdown_sig.clone(),
+    ));
     all_tasks.spawn(xread::xread_task(
         Arc::clone(storage),
         Arc::clone(&queues),
@@ -1,23 +1,36 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
 
 use bytes::Bytes;
 use tokio::sync::{mpsc, oneshot, watch};
 
-use crate::storage::list::{ListDirection, ListStorage};
+use crate::storage::{
+    list::{ListDirection, ListStorage},
+    StorageResult,
+};
 
 use super::Queues;
 
-/// A blocking pop client waiting for a value
+/// A blocking pop client waiting for a value. A client waiting on several keys is served
+/// once, from the first of its keys to receive elements.
 #[derive(Debug)]
 pub struct BPopClient {
-    pub key: Bytes,
+    pub keys: Vec<Bytes>,
     pub dir: ListDirection,
-    pub tx: oneshot::Sender<Bytes>,
+    /// The maximum number of elements to pop
+    pub count: usize,
+    /// The destination list and direction, if the client is moving the element (BLMOVE)
+    pub dest: Option<(Bytes, ListDirection)>,
+    /// Sends the key that was popped from and the popped elements
+    pub tx: oneshot::Sender<StorageResult<(Bytes, Vec<Bytes>)>>,
 }
 
 /// Task that manages the queue of blocking pop clients. Listens for changed
 /// key events via a channel, and then pops and sends list elements to any
-/// waiting clients.
+/// waiting clients. Elements moved to another list can also be sent to the
+/// clients waiting on that list.
 pub async fn bpop_task(
     storage: Arc<Mutex<impl ListStorage>>,
     queues: Arc<Queues>,
@@ -39,26 +52,44 @@ pub async fn bpop_task(
         let mut storage_lock = storage.lock().unwrap();
         let mut bpop_queue = queues.bpop_lock();
 
-        // Iterate over the bpop queue, looking for blocking clients waiting on this key.
-        while let Some(client_idx) = bpop_queue.iter().position(|c| c.key == key) {
-            // Check if this client's channel/receiver has been dropped
-            if bpop_queue[client_idx].tx.is_closed() {
-                bpop_queue.remove(client_idx);
-                continue;
-            }
+        // Keys to check for waiting clients (moving an element adds the destination key)
+        let mut keys = VecDeque::from([key]);
+        while let Some(key) = keys.pop_front() {
+            // Iterate over the bpop queue, looking for blocking clients waiting on this key.
+            while let Some(client_idx) = bpop_queue.iter().position(|c| c.keys.contains(&key)) {
+                // Check if this client's channel/receiver has been dropped
+                if bpop_queue[client_idx].tx.is_closed() {
+                    bpop_queue.remove(client_idx);
+                    continue;
+                }
+
+                // Pop (or move) the element with the client's chosen direction
+                let client = &bpop_queue[client_idx];
+                let result = match &client.dest {
+                    None => {
+                        let count = client.count.try_into().unwrap_or(i64::MAX);
+                        storage_lock
+                            .pop(&key, client.dir, count)
+                            .map(|popped| Ok((key.clone(), popped)))
+                    }
+                    Some((dest, to)) => storage_lock
+                        .lmove(&key, dest.clone(), client.dir, *to)
+                        .transpose()
+                        .map(|moved| moved.map(|elem| (key.clone(), vec![elem]))),
+                };
+                let Some(result) = result else {
+                    // No more elements in list
+                    break;
+                };
 
-            // Pop the element with the client's chosen direction
-            let direction = bpop_queue[client_idx].dir;
-            if let Some(mut popped) = storage_lock.pop(&key, direction, 1) {
                 // Remove the blocking client from the queue
                 let client = bpop_queue.remove(client_idx).expect("valid idx");
-                let elem = popped.pop().expect("pop() should return 1 item");
+                if let (Ok(_), Some((dest, _))) = (&result, client.dest) {
+                    keys.push_back(dest);
+                }
 
                 // Send the response to client
-                client.tx.send(elem).ok();
-            } else {
-                // No more elements in list
-                break;
+                client.tx.send(result).ok();
             }
         }
     }
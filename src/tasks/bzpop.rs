@@ -0,0 +1,70 @@
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use tokio::sync::{mpsc, oneshot, watch};
+
+use crate::storage::{
+    sorted_set::{KeyAndScoredMembers, SortedSetStorage},
+    StorageResult,
+};
+
+use super::Queues;
+
+/// A blocking sorted set pop client waiting for members. A client waiting on several keys
+/// is served once, from the first of its keys to receive members.
+#[derive(Debug)]
+pub struct BZPopClient {
+    pub keys: Vec<Bytes>,
+    /// Pop the highest scores instead of the lowest
+    pub max: bool,
+    /// The maximum number of members to pop
+    pub count: usize,
+    /// Sends the key that was popped from and the popped members with their scores
+    pub tx: oneshot::Sender<StorageResult<KeyAndScoredMembers>>,
+}
+
+/// Task that manages the queue of blocking sorted set pop clients. Listens for changed
+/// key events via a channel, and then pops and sends members to any waiting clients.
+pub async fn bzpop_task(
+    storage: Arc<Mutex<impl SortedSetStorage>>,
+    queues: Arc<Queues>,
+    mut bzpop_rx: mpsc::UnboundedReceiver<Bytes>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    loop {
+        let key = tokio::select! {
+            opt = bzpop_rx.recv()=> {
+                match opt {
+                    Some(key) => key,
+                    None => break,
+                }
+            },
+            _ = shutdown.changed() => break
+        };
+
+        // Get locks on the data storage and bzpop queue
+        let mut storage_lock = storage.lock().unwrap();
+        let mut bzpop_queue = queues.bzpop_lock();
+
+        // Iterate over the bzpop queue, looking for blocking clients waiting on this key.
+        while let Some(client_idx) = bzpop_queue.iter().position(|c| c.keys.contains(&key)) {
+            // Check if this client's channel/receiver has been dropped
+            if bzpop_queue[client_idx].tx.is_closed() {
+                bzpop_queue.remove(client_idx);
+                continue;
+            }
+
+            // Pop the members with the client's chosen order
+            let client = &bzpop_queue[client_idx];
+            let result = match storage_lock.zpop(&key, client.max, client.count) {
+                Ok(popped) if popped.is_empty() => break, // No more members in sorted set
+                Ok(popped) => Ok((key.clone(), popped)),
+                Err(err) => Err(err),
+            };
+
+            // Remove the blocking client from the queue and send the response
+            let client = bzpop_queue.remove(client_idx).expect("valid idx");
+            client.tx.send(result).ok();
+        }
+    }
+}
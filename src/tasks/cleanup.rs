@@ -9,7 +9,7 @@ use crate::storage::Storage;
 
 use super::Queues;
 
-/// Task to periodically cleanup expired keys and disconnected blocking clients
+/// Task to periodically cleanup expired keys, expired hash fields, and disconnected blocking clients
 pub async fn cleanup_task(
     storage: Arc<Mutex<impl Storage>>,
     queues: Arc<Queues>,
@@ -22,9 +22,9 @@ pub async fn cleanup_task(
             _ = shutdown.changed() => break
         }
 
-        let expired_count = storage.lock().unwrap().cleanup_expired();
+        let (expired_keys, expired_fields) = storage.lock().unwrap().cleanup_expired();
         queues.cleanup_disconnected();
 
-        debug!("cleanup task: {expired_count} expired");
+        debug!("cleanup task: {expired_keys} expired keys, {expired_fields} expired hash fields");
     }
 }
@@ -9,6 +9,7 @@ use super::{counters::ChangeCounter, pubsub::PubSubEvent};
 /// Holds the senders and counters to notify tasks of certain events
 pub struct Notifiers {
     pub(super) bpop: mpsc::UnboundedSender<Bytes>,
+    pub(super) bzpop: mpsc::UnboundedSender<Bytes>,
     pub(super) xread: mpsc::UnboundedSender<Bytes>,
     pub(super) pubsub: mpsc::UnboundedSender<PubSubEvent>,
     pub(super) counters: Arc<ChangeCounter>,
@@ -27,6 +28,13 @@ impl Notifiers {
         }
     }
 
+    /// Notify blocking sorted set pop task that members were added to a sorted set
+    pub fn bzpop_notify(&self, zset_key: Bytes) {
+        if self.bzpop.send(zset_key).is_err() {
+            warn!("Blocking sorted set pop receiver was dropped");
+        }
+    }
+
     /// Notify blocking xread task that an entry was added to a stream
     pub fn xread_notify(&self, stream_key: Bytes) {
         if self.xread.send(stream_key).is_err() {
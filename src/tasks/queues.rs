@@ -5,22 +5,71 @@ use fxhash::FxHashMap;
 use tinikeyval_protocol::RespValue;
 use tokio::sync::{mpsc, oneshot};
 
-use crate::storage::{list::ListDirection, stream::StreamKeyAndEntries};
+use crate::storage::{
+    list::ListDirection, sorted_set::KeyAndScoredMembers, stream::StreamKeyAndEntries,
+    StorageResult,
+};
 
-use super::{bpop::BPopClient, pubsub::PubSubClient, xread::XReadClient};
+use super::{bpop::BPopClient, bzpop::BZPopClient, pubsub::PubSubClient, xread::XReadClient};
 
 /// Holds the queues for blocking operations, pub/sub, etc.
 #[derive(Debug, Default)]
 pub struct Queues {
     bpop: Mutex<VecDeque<BPopClient>>,
+    bzpop: Mutex<VecDeque<BZPopClient>>,
     xread: Mutex<Vec<XReadClient>>,
     pubsub: Mutex<FxHashMap<u64, PubSubClient>>,
 }
 
 impl Queues {
-    /// Enqueue a blocking pop client
-    pub fn bpop_push(&self, key: Bytes, dir: ListDirection, tx: oneshot::Sender<Bytes>) {
-        self.bpop_lock().push_back(BPopClient { key, dir, tx });
+    /// Enqueue a blocking pop client waiting on one or more keys
+    pub fn bpop_push(
+        &self,
+        keys: Vec<Bytes>,
+        dir: ListDirection,
+        count: usize,
+        tx: oneshot::Sender<StorageResult<(Bytes, Vec<Bytes>)>>,
+    ) {
+        self.bpop_lock().push_back(BPopClient {
+            keys,
+            dir,
+            count,
+            dest: None,
+            tx,
+        });
+    }
+
+    /// Enqueue a blocking move client, that will push the popped element to the destination list
+    pub fn bpop_move_push(
+        &self,
+        key: Bytes,
+        dir: ListDirection,
+        dest: (Bytes, ListDirection),
+        tx: oneshot::Sender<StorageResult<(Bytes, Vec<Bytes>)>>,
+    ) {
+        self.bpop_lock().push_back(BPopClient {
+            keys: vec![key],
+            dir,
+            count: 1,
+            dest: Some(dest),
+            tx,
+        });
+    }
+
+    /// Enqueue a blocking sorted set pop client waiting on one or more keys
+    pub fn bzpop_push(
+        &self,
+        keys: Vec<Bytes>,
+        max: bool,
+        count: usize,
+        tx: oneshot::Sender<StorageResult<KeyAndScoredMembers>>,
+    ) {
+        self.bzpop_lock().push_back(BZPopClient {
+            keys,
+            max,
+            count,
+            tx,
+        });
     }
 
     /// Add a blocking xread client
@@ -51,6 +100,11 @@ impl Queues {
         self.bpop.lock().unwrap()
     }
 
+    /// Get an exclusive lock on the blocking sorted set pop queue
+    pub(super) fn bzpop_lock(&self) -> std::sync::MutexGuard<'_, VecDeque<BZPopClient>> {
+        self.bzpop.lock().unwrap()
+    }
+
     /// Get an exclusive lock on the blocking xread clients
     pub(super) fn xread_lock(&self) -> std::sync::MutexGuard<'_, Vec<XReadClient>> {
         self.xread.lock().unwrap()
@@ -64,6 +118,7 @@ impl Queues {
     /// Remove any disconnected/defunct clients
     pub(super) fn cleanup_disconnected(&self) {
         self.bpop_lock().retain(|client| !client.tx.is_closed());
+        self.bzpop_lock().retain(|client| !client.tx.is_closed());
         self.xread_lock()
             .retain(|client| !client.tx.as_ref().is_none_or(|tx| tx.is_closed()));
         self.pubsub_lock()